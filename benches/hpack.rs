@@ -0,0 +1,131 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use http2::hpack::decoder::Decoder;
+use http2::hpack::encoder::{Encoder, HuffmanPolicy};
+use http2::hpack::header::Header;
+
+// sequential header blocks on one connection
+const BLOCKS: usize = 100;
+
+fn browser_gets() -> Vec<Vec<Header>> {
+    (0..BLOCKS)
+        .map(|i| vec![
+            Header::new(":method", "GET"),
+            Header::new(":scheme", "https"),
+            Header::new(":authority", "www.example.com"),
+            Header::new(":path", format!("/assets/{}/app.{}.js?v={}", i % 7, i, i * 31)),
+            Header::new("user-agent", "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0 Safari/537.36"),
+            Header::new("accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8"),
+            Header::new("accept-encoding", "gzip, deflate, br"),
+            Header::new("accept-language", "en-US,en;q=0.9"),
+            Header::new("referer", format!("https://www.example.com/page/{}", i % 5)),
+            Header::new("cookie", "session=8f14e45fceea167a5a36dedd4bea2543; theme=dark; consent=yes"),
+            Header::new("sec-fetch-mode", "no-cors"),
+            Header::new("sec-fetch-site", "same-origin"),
+        ])
+        .collect()
+}
+
+fn grpc_calls() -> Vec<Vec<Header>> {
+    (0..BLOCKS)
+        .map(|i| {
+            if i % 2 == 0 {
+                vec![
+                    Header::new(":method", "POST"),
+                    Header::new(":scheme", "http"),
+                    Header::new(":path", format!("/helloworld.Greeter/{}", ["SayHello", "SayGoodbye", "Ping"][i % 3])),
+                    Header::new(":authority", "greeter.internal:50051"),
+                    Header::new("content-type", "application/grpc"),
+                    Header::new("te", "trailers"),
+                    Header::new("grpc-timeout", format!("{}m", 100 + i % 50)),
+                    Header::new("grpc-accept-encoding", "identity,deflate,gzip"),
+                    Header::new("user-agent", "grpc-rust/0.1.0"),
+                    Header::new("x-request-id", format!("{:032x}", i * 2654435761)),
+                ]
+            } else {
+                vec![
+                    Header::new(":status", "200"),
+                    Header::new("content-type", "application/grpc"),
+                    Header::new("grpc-encoding", "identity"),
+                    Header::new("grpc-status", "0"),
+                    Header::new("grpc-message", ""),
+                ]
+            }
+        })
+        .collect()
+}
+
+fn cookie_responses() -> Vec<Vec<Header>> {
+    (0..BLOCKS)
+        .map(|i| {
+            let mut headers = vec![
+                Header::new(":status", "200"),
+                Header::new("content-type", "text/html; charset=utf-8"),
+                Header::new("cache-control", "private, max-age=0, no-cache"),
+                Header::new("date", format!("Mon, 21 Oct 2013 20:{:02}:{:02} GMT", i / 60 % 60, i % 60)),
+                Header::new("server", "nginx"),
+                Header::new("strict-transport-security", "max-age=31536000; includeSubDomains"),
+            ];
+            for j in 0..8 {
+                let value = format!(
+                    "tracker_{}={:064x}; Path=/; Domain=.example.com; Max-Age=31536000; Secure; HttpOnly; SameSite=Lax",
+                    j, (i * 8 + j).wrapping_mul(11400714819323198485),
+                );
+                headers.push(Header::new("set-cookie", value));
+            }
+            headers
+        })
+        .collect()
+}
+
+fn encoder(huffman: HuffmanPolicy) -> Encoder {
+    Encoder::builder().huffman(huffman).build()
+}
+
+fn bench_workload(c: &mut Criterion, name: &str, blocks: &[Vec<Header>]) {
+    let headers: usize = blocks.iter().map(|x| x.len()).sum();
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(headers as u64));
+
+    for (label, huffman) in [("plain", HuffmanPolicy::Never), ("huffman", HuffmanPolicy::Shortest)] {
+        let mut sizing = encoder(huffman);
+        let encoded: Vec<Vec<u8>> = blocks.iter().map(|x| sizing.encode(x)).collect();
+        let stats = sizing.stats();
+        println!(
+            "{}/{}: {} headers, {:.1} raw bytes/header, {:.1} encoded bytes/header",
+            name, label, stats.headers,
+            stats.raw_bytes as f64 / stats.headers as f64,
+            stats.encoded_bytes as f64 / stats.headers as f64,
+        );
+
+        group.bench_function(BenchmarkId::new("encode", label), |b| {
+            b.iter(|| {
+                let mut encoder = encoder(huffman);
+                for block in blocks {
+                    black_box(encoder.encode(black_box(block)));
+                }
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("decode", label), |b| {
+            b.iter(|| {
+                let mut decoder = Decoder::new();
+                for block in &encoded {
+                    black_box(decoder.decode(black_box(block)).unwrap());
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// Criterion reports time per iteration and headers per second, ns/header is
+// the inverse of the latter. Bytes per header are printed before each group.
+fn benches(c: &mut Criterion) {
+    bench_workload(c, "browser_get", &browser_gets());
+    bench_workload(c, "grpc", &grpc_calls());
+    bench_workload(c, "cookie_response", &cookie_responses());
+}
+
+criterion_group!(hpack, benches);
+criterion_main!(hpack);
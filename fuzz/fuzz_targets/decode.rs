@@ -0,0 +1,27 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use http2::hpack::decoder::{Decoder, DecoderPolicy};
+
+// Arbitrary header blocks must only ever produce errors, never panics.
+fuzz_target!(|data: &[u8]| {
+    let mut decoder = Decoder::builder()
+        .max_header_list_size(16384)
+        .policy(DecoderPolicy {
+            max_string_length: 16384,
+            max_field_count: 1024,
+            max_expansion_ratio: 64,
+            expansion_ratio_threshold: 4096,
+        })
+        .build();
+
+    // the first byte splits the input so blocks share a dynamic table
+    let Some((split, data)) = data.split_first() else { return };
+    let (first, second) = data.split_at((*split as usize).min(data.len()));
+    let _ = decoder.decode_headers(first);
+    let _ = decoder.decode_headers(second);
+
+    let size = decoder.header_table().entries().map(|x| x.header.size()).sum::<usize>();
+    assert_eq!(decoder.header_table().size(), size);
+    assert!(decoder.header_table().size() <= decoder.header_table_size());
+});
@@ -0,0 +1,70 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use http2::hpack::decoder::{Decoder, DecoderPolicy};
+use http2::hpack::encoder::{Encoder, HuffmanPolicy, IndexingStrategy};
+use http2::hpack::header::EncodableHeader;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    max_table_size: u16,
+    huffman: u8,
+    without_indexing: bool,
+    blocks: Vec<Block>,
+}
+
+#[derive(Debug, Arbitrary)]
+struct Block {
+    // applied before the block, clamped to the maximum
+    table_size: Option<u16>,
+    headers: Vec<(String, String, bool)>,
+}
+
+// Whatever the encoder emits decodes to the same headers and leaves both
+// dynamic tables identical.
+fuzz_target!(|input: Input| {
+    let max_table_size = input.max_table_size as usize;
+    let huffman = match input.huffman % 3 {
+        0 => HuffmanPolicy::Never,
+        1 => HuffmanPolicy::Always,
+        _ => HuffmanPolicy::Shortest,
+    };
+    let indexing = if input.without_indexing {
+        IndexingStrategy::WithoutIndexing
+    } else {
+        IndexingStrategy::Incremental
+    };
+
+    let mut encoder = Encoder::builder()
+        .max_header_table_size(max_table_size)
+        .header_table_size(max_table_size)
+        .huffman(huffman)
+        .indexing(indexing)
+        .build();
+    // the default limits reject large but valid blocks, which are not bugs here
+    let policy = DecoderPolicy {
+        max_string_length: usize::MAX,
+        max_field_count: usize::MAX,
+        max_expansion_ratio: usize::MAX,
+        expansion_ratio_threshold: usize::MAX,
+    };
+    let mut decoder = Decoder::builder()
+        .max_header_table_size(max_table_size)
+        .policy(policy)
+        .build();
+
+    for block in input.blocks {
+        if let Some(size) = block.table_size {
+            encoder.set_header_table_size(size as usize);
+        }
+
+        let headers: Vec<EncodableHeader> = block.headers.into_iter()
+            .map(|(name, value, is_sensitive)| EncodableHeader::new(name, value, is_sensitive))
+            .collect();
+        let encoded = encoder.encode_headers(&headers);
+
+        assert_eq!(decoder.decode_headers(&encoded).unwrap(), headers);
+        assert_eq!(decoder.header_table().snapshot(), encoder.header_table().snapshot());
+    }
+});
@@ -0,0 +1,279 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+use http2::hpack::decoder::Decoder;
+use http2::hpack::disassembler;
+use http2::hpack::encoder::{Encoder, HuffmanPolicy};
+use http2::hpack::header::EncodableHeader;
+use http2::hpack::hex;
+
+const USAGE: &str = "\
+usage: hpack encode [options] [FILE]
+       hpack decode [options] [FILE]
+
+encode reads header lists as `name: value` lines, separated by blank lines,
+or with --json as an array of header lists, and prints one hex block per list.
+decode reads one hex block per line, or with --binary a single raw block, and
+prints the header lists. Blocks share one dynamic table, in order.
+
+options:
+  --table-size N      SETTINGS_HEADER_TABLE_SIZE, default 4096
+  --huffman MODE      never, always or shortest (encode), default shortest
+  --json              read (encode) or print (decode) JSON header lists
+  --binary            decode a raw block instead of hex lines
+  -v, --verbose       print an annotated trace of every block
+  -h, --help          print this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Encode,
+    Decode,
+}
+
+struct Options {
+    command: Command,
+    table_size: usize,
+    huffman: HuffmanPolicy,
+    json: bool,
+    binary: bool,
+    verbose: bool,
+    path: Option<String>,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|x| x == "-h" || x == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let result = parse_args(&args).and_then(|options| {
+        let input = read_input(options.path.as_deref())?;
+        match options.command {
+            Command::Encode => encode(&options, &input),
+            Command::Decode => decode(&options, &input),
+        }
+    });
+
+    if let Err(e) = result {
+        eprintln!("hpack: {}", e);
+        process::exit(1);
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter();
+    let command = match args.next().map(|x| x.as_str()) {
+        Some("encode") => Command::Encode,
+        Some("decode") => Command::Decode,
+        Some(x) => return Err(format!("unknown command: {}\n\n{}", x, USAGE)),
+        None => return Err(USAGE.to_string()),
+    };
+
+    let mut options = Options {
+        command,
+        table_size: 4096,
+        huffman: HuffmanPolicy::Shortest,
+        json: false,
+        binary: false,
+        verbose: false,
+        path: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--table-size" => {
+                let value = args.next().ok_or("missing value for --table-size")?;
+                options.table_size = value.parse().map_err(|_| format!("invalid table size: {}", value))?;
+            }
+            "--huffman" => {
+                options.huffman = match args.next().map(|x| x.as_str()) {
+                    Some("never") => HuffmanPolicy::Never,
+                    Some("always") => HuffmanPolicy::Always,
+                    Some("shortest") => HuffmanPolicy::Shortest,
+                    Some(x) => return Err(format!("invalid huffman mode: {}", x)),
+                    None => return Err("missing value for --huffman".to_string()),
+                };
+            }
+            "--json" => options.json = true,
+            "--binary" => options.binary = true,
+            "-v" | "--verbose" => options.verbose = true,
+            x if x.starts_with('-') && x != "-" => return Err(format!("unknown option: {}", x)),
+            x if options.path.is_none() => options.path = Some(x.to_string()),
+            x => return Err(format!("unexpected argument: {}", x)),
+        }
+    }
+
+    Ok(options)
+}
+
+fn read_input(path: Option<&str>) -> Result<Vec<u8>, String> {
+    match path {
+        Some(x) if x != "-" => fs::read(x).map_err(|e| format!("{}: {}", x, e)),
+        _ => {
+            let mut input = vec![];
+            io::stdin().read_to_end(&mut input).map_err(|e| e.to_string())?;
+            Ok(input)
+        }
+    }
+}
+
+fn encode(options: &Options, input: &[u8]) -> Result<(), String> {
+    let text = std::str::from_utf8(input).map_err(|_| "input is not UTF-8")?;
+    let blocks = if options.json { parse_json(text)? } else { parse_lines(text)? };
+
+    let mut encoder = Encoder::builder()
+        .max_header_table_size(options.table_size)
+        .header_table_size(options.table_size)
+        .huffman(options.huffman)
+        .build();
+    // only used to print the trace, it follows the encoder's table
+    let mut decoder = Decoder::builder().max_header_table_size(options.table_size).build();
+
+    let mut stdout = io::stdout().lock();
+    for headers in blocks {
+        let encoded = encoder.encode_headers(&headers);
+        writeln!(stdout, "{}", hex::encode(&encoded)).map_err(|e| e.to_string())?;
+        if options.verbose {
+            write!(stdout, "{}", disassembler::disassemble(&mut decoder, &encoded)).map_err(|e| e.to_string())?;
+            writeln!(stdout).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn decode(options: &Options, input: &[u8]) -> Result<(), String> {
+    let blocks = if options.binary {
+        vec![input.to_vec()]
+    } else {
+        let text = std::str::from_utf8(input).map_err(|_| "input is not UTF-8")?;
+        text.lines()
+            .filter(|x| !x.trim().is_empty() && !x.trim_start().starts_with('#'))
+            .map(|x| hex::decode(x).map_err(|e| format!("invalid hex: {}: {}", x.trim(), e)))
+            .collect::<Result<Vec<Vec<u8>>, String>>()?
+    };
+
+    let mut decoder = Decoder::builder().max_header_table_size(options.table_size).build();
+    let mut decoded: Vec<Vec<EncodableHeader>> = vec![];
+    let mut stdout = io::stdout().lock();
+
+    for (i, block) in blocks.iter().enumerate() {
+        if options.verbose {
+            let disassembly = disassembler::disassemble(&mut decoder, block);
+            writeln!(stdout, "# block {}\n{}", i, disassembly).map_err(|e| e.to_string())?;
+            if let Some((offset, e)) = disassembly.error {
+                return Err(format!("block {}: offset {}: {}", i, offset, e));
+            }
+            continue;
+        }
+
+        let headers = decoder.decode_headers(block).map_err(|e| format!("block {}: {}", i, e))?;
+        if options.json {
+            decoded.push(headers);
+            continue;
+        }
+
+        if i > 0 {
+            writeln!(stdout).map_err(|e| e.to_string())?;
+        }
+        for header in headers {
+            writeln!(stdout, "{}: {}", header.name, header.value).map_err(|e| e.to_string())?;
+        }
+    }
+
+    if options.json && !options.verbose {
+        let json = serde_json::to_string_pretty(&decoded).map_err(|e| e.to_string())?;
+        writeln!(stdout, "{}", json).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+// header lists of `name: value` lines, separated by blank lines
+fn parse_lines(text: &str) -> Result<Vec<Vec<EncodableHeader>>, String> {
+    let mut blocks: Vec<Vec<EncodableHeader>> = vec![];
+    let mut headers: Vec<EncodableHeader> = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            if !headers.is_empty() {
+                blocks.push(std::mem::take(&mut headers));
+            }
+            continue;
+        }
+
+        // pseudo-header names start with a colon, so split after the first character
+        let split = line.get(1..)
+            .and_then(|x| x.find(':'))
+            .ok_or(format!("line {}: expected `name: value`", i + 1))?;
+        let (name, value) = line.split_at(split + 1);
+        headers.push(EncodableHeader::new(name.trim(), value[1..].trim(), false));
+    }
+
+    if !headers.is_empty() {
+        blocks.push(headers);
+    }
+    Ok(blocks)
+}
+
+// either a single header list or an array of them
+fn parse_json(text: &str) -> Result<Vec<Vec<EncodableHeader>>, String> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let is_single = value.as_array().is_some_and(|x| x.first().is_some_and(|x| x.is_object()));
+
+    if is_single {
+        let headers = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        return Ok(vec![headers]);
+    }
+
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use http2::hpack::header::EncodableHeader;
+    use super::{parse_args, parse_json, parse_lines, Command};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let blocks = parse_lines(":method: GET\n:path: /\n\n\ncookie: a=b: c\n").unwrap();
+        assert_eq!(blocks, vec![
+            vec![EncodableHeader::new(":method", "GET", false), EncodableHeader::new(":path", "/", false)],
+            vec![EncodableHeader::new("cookie", "a=b: c", false)],
+        ]);
+
+        assert!(parse_lines("").unwrap().is_empty());
+        assert_eq!(parse_lines(":method: GET\nnocolon").unwrap_err(), "line 2: expected `name: value`");
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let single = parse_json(r#"[{"name": "a", "value": "1"}]"#).unwrap();
+        assert_eq!(single, vec![vec![EncodableHeader::new("a", "1", false)]]);
+
+        let lists = parse_json(r#"[[{"name": "a", "value": "1"}], []]"#).unwrap();
+        assert_eq!(lists, vec![vec![EncodableHeader::new("a", "1", false)], vec![]]);
+
+        assert!(parse_json("{").is_err());
+        assert!(parse_json(r#"{"name": "a"}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(&args(&["decode", "--table-size", "256", "-v", "blocks.txt"])).unwrap();
+        assert_eq!(options.command, Command::Decode);
+        assert_eq!(options.table_size, 256);
+        assert!(options.verbose);
+        assert_eq!(options.path.as_deref(), Some("blocks.txt"));
+
+        assert!(parse_args(&args(&["encode", "--table-size", "x"])).is_err());
+        assert!(parse_args(&args(&["encode", "--huffman"])).is_err());
+        assert!(parse_args(&args(&["encode", "a", "b"])).is_err());
+        assert!(parse_args(&args(&["transcode"])).is_err());
+    }
+}
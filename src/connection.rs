@@ -0,0 +1,703 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+use crate::frame::codec;
+use crate::frame::error::{ErrorCode, FrameError};
+use crate::frame::header_block::{self, HeaderBlock, HeaderBlockAssembler, HeaderBlockError};
+use crate::frame::preface::{Handshake, PrefaceError, Role, DEFAULT_SETTINGS_TIMEOUT};
+use crate::frame::settings::{ConnectionSettings, SettingsError, DEFAULT_INITIAL_WINDOW_SIZE, MAX_WINDOW_SIZE};
+use crate::frame::types::*;
+use crate::hpack::decoder::Decoder;
+use crate::hpack::encoder::Encoder;
+use crate::hpack::header::EncodableHeader;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionError {
+    Preface(PrefaceError),
+    Frame(FrameError),
+    HeaderBlock(HeaderBlockError),
+    Settings(SettingsError),
+    // frame on an idle stream or one the peer can not open
+    InvalidStream(u32),
+    // WINDOW_UPDATE of 0 for the connection
+    InvalidWindowUpdate,
+    // connection window above 2^31-1
+    FlowControl,
+    // PUSH_PROMISE while push is disabled
+    UnexpectedPush,
+    // local errors, nothing is sent to the peer
+    Closed,
+    NotClient,
+    StreamLimit,
+    StreamNotOpen(u32),
+}
+
+impl ConnectionError {
+
+    // error code for the GOAWAY frame
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ConnectionError::Preface(e) => e.code(),
+            ConnectionError::Frame(e) => e.code(),
+            ConnectionError::HeaderBlock(e) => e.code(),
+            ConnectionError::Settings(e) => e.code(),
+            ConnectionError::InvalidStream(_)
+            | ConnectionError::InvalidWindowUpdate
+            | ConnectionError::UnexpectedPush => ErrorCode::ProtocolError,
+            ConnectionError::FlowControl => ErrorCode::FlowControlError,
+            ConnectionError::Closed
+            | ConnectionError::NotClient
+            | ConnectionError::StreamLimit
+            | ConnectionError::StreamNotOpen(_) => ErrorCode::InternalError,
+        }
+    }
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionError::Preface(e) => e.fmt(f),
+            ConnectionError::Frame(e) => e.fmt(f),
+            ConnectionError::HeaderBlock(e) => e.fmt(f),
+            ConnectionError::Settings(e) => e.fmt(f),
+            ConnectionError::InvalidStream(x) => write!(f, "Frame not allowed on stream {}!", x),
+            ConnectionError::InvalidWindowUpdate => f.write_str("Connection window increment of 0!"),
+            ConnectionError::FlowControl => f.write_str("Flow control window overflow!"),
+            ConnectionError::UnexpectedPush => f.write_str("PUSH_PROMISE while push is disabled!"),
+            ConnectionError::Closed => f.write_str("Connection closed!"),
+            ConnectionError::NotClient => f.write_str("Only clients open streams!"),
+            ConnectionError::StreamLimit => f.write_str("Peer's stream limit reached!"),
+            ConnectionError::StreamNotOpen(x) => write!(f, "Stream {} is not open for sending!", x),
+        }
+    }
+}
+
+impl core::error::Error for ConnectionError {}
+
+impl From<PrefaceError> for ConnectionError {
+    fn from(e: PrefaceError) -> Self {
+        ConnectionError::Preface(e)
+    }
+}
+
+impl From<FrameError> for ConnectionError {
+    fn from(e: FrameError) -> Self {
+        ConnectionError::Frame(e)
+    }
+}
+
+impl From<HeaderBlockError> for ConnectionError {
+    fn from(e: HeaderBlockError) -> Self {
+        ConnectionError::HeaderBlock(e)
+    }
+}
+
+impl From<SettingsError> for ConnectionError {
+    fn from(e: SettingsError) -> Self {
+        ConnectionError::Settings(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    // opened by the peer, followed by its headers
+    NewStream(u32),
+    Headers {
+        stream_id: u32,
+        headers: Vec<EncodableHeader>,
+        end_stream: bool,
+    },
+    Data {
+        stream_id: u32,
+        data: Vec<u8>,
+        end_stream: bool,
+    },
+    Reset {
+        stream_id: u32,
+        error_code: ErrorCode,
+    },
+    GoAway {
+        last_stream_id: u32,
+        error_code: ErrorCode,
+        debug_data: Vec<u8>,
+    },
+}
+
+struct Stream {
+    // END_STREAM sent
+    local_closed: bool,
+    // END_STREAM received
+    remote_closed: bool,
+    send_window: i64,
+}
+
+// encoded header block, HEADERS plus CONTINUATION frames, like the decoder's
+// 64 KiB default limits
+pub const DEFAULT_MAX_HEADER_BLOCK_SIZE: usize = 65_536;
+
+pub struct ConnectionBuilder {
+    role: Role,
+    settings: Vec<Setting>,
+    encoder: Option<Encoder>,
+    decoder: Option<Decoder>,
+    settings_timeout: Duration,
+    max_header_block_size: usize,
+}
+
+impl ConnectionBuilder {
+
+    pub fn new(role: Role) -> Self {
+        Self {
+            role,
+            settings: Vec::new(),
+            encoder: None,
+            decoder: None,
+            settings_timeout: DEFAULT_SETTINGS_TIMEOUT,
+            max_header_block_size: DEFAULT_MAX_HEADER_BLOCK_SIZE,
+        }
+    }
+
+    // initial SETTINGS, sent with the preface
+    pub fn settings(mut self, params: Vec<Setting>) -> Self {
+        self.settings = params;
+        self
+    }
+
+    pub fn encoder(mut self, encoder: Encoder) -> Self {
+        self.encoder = Some(encoder);
+        self
+    }
+
+    pub fn decoder(mut self, decoder: Decoder) -> Self {
+        self.decoder = Some(decoder);
+        self
+    }
+
+    pub fn settings_timeout(mut self, timeout: Duration) -> Self {
+        self.settings_timeout = timeout;
+        self
+    }
+
+    // larger blocks fail the connection with ENHANCE_YOUR_CALM
+    pub fn max_header_block_size(mut self, size: usize) -> Self {
+        self.max_header_block_size = size;
+        self
+    }
+
+    // now starts the SETTINGS timeout, see Connection::check_timeout
+    pub fn build(self, now: Duration) -> Result<Connection, ConnectionError> {
+        let mut handshake = Handshake::new(self.role);
+        handshake.set_settings_timeout(self.settings_timeout);
+        let mut assembler = HeaderBlockAssembler::new();
+        assembler.set_max_block_size(self.max_header_block_size);
+
+        let mut connection = Connection {
+            role: self.role,
+            handshake,
+            settings: ConnectionSettings::new(),
+            encoder: self.encoder.unwrap_or_default(),
+            decoder: self.decoder.unwrap_or_default(),
+            assembler,
+            streams: BTreeMap::new(),
+            next_stream_id: if self.role == Role::Client { 1 } else { 2 },
+            last_peer_stream_id: 0,
+            send_window: DEFAULT_INITIAL_WINDOW_SIZE as i64,
+            buffer: Vec::new(),
+            output: Vec::new(),
+            events: VecDeque::new(),
+            goaway_sent: false,
+            goaway_received: false,
+            closed: false,
+        };
+        connection.handshake.start(
+            self.settings,
+            &mut connection.settings,
+            &mut connection.decoder,
+            now,
+            &mut connection.output,
+        )?;
+
+        Ok(connection)
+    }
+}
+
+// Sans-IO HTTP/2 connection. Received bytes go into receive, bytes to send
+// come out of take_output and everything else is reported as events. Stream
+// and connection windows of received data are handed back right away.
+pub struct Connection {
+    role: Role,
+    handshake: Handshake,
+    settings: ConnectionSettings,
+    encoder: Encoder,
+    decoder: Decoder,
+    assembler: HeaderBlockAssembler,
+    streams: BTreeMap<u32, Stream>,
+    // next stream we open
+    next_stream_id: u32,
+    // highest stream opened or promised by the peer
+    last_peer_stream_id: u32,
+    send_window: i64,
+    // received bytes not yet parsed
+    buffer: Vec<u8>,
+    output: Vec<u8>,
+    events: VecDeque<Event>,
+    goaway_sent: bool,
+    goaway_received: bool,
+    // after a connection error
+    closed: bool,
+}
+
+impl Connection {
+
+    pub fn new(role: Role, now: Duration) -> Self {
+        // without settings there is nothing to reject
+        Self::builder(role).build(now).unwrap()
+    }
+
+    pub fn builder(role: Role) -> ConnectionBuilder {
+        ConnectionBuilder::new(role)
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn settings(&self) -> &ConnectionSettings {
+        &self.settings
+    }
+
+    pub fn encoder(&self) -> &Encoder {
+        &self.encoder
+    }
+
+    pub fn decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.handshake.is_complete()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    // bytes to write to the transport
+    pub fn take_output(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.output)
+    }
+
+    pub fn poll_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    // Connection errors send GOAWAY, then close the connection. Stream errors
+    // only reset their stream.
+    pub fn receive(&mut self, data: &[u8]) -> Result<(), ConnectionError> {
+        if self.closed {
+            return Err(ConnectionError::Closed);
+        }
+
+        self.buffer.extend_from_slice(data);
+        self.process().inspect_err(|e| self.fail(e))
+    }
+
+    // when check_timeout fails unless the peer acknowledges our SETTINGS
+    pub fn deadline(&self) -> Option<Duration> {
+        self.handshake.deadline()
+    }
+
+    pub fn check_timeout(&mut self, now: Duration) -> Result<(), ConnectionError> {
+        if self.closed {
+            return Err(ConnectionError::Closed);
+        }
+
+        self.handshake.check_timeout(now).map_err(ConnectionError::from).inspect_err(|e| self.fail(e))
+    }
+
+    pub fn update_settings(&mut self, params: Vec<Setting>) -> Result<(), ConnectionError> {
+        self.check_open()?;
+        let frame = self.settings.send(params, &mut self.decoder)?;
+        self.write(&frame);
+        Ok(())
+    }
+
+    // Opens the next client stream with its request headers.
+    pub fn open_stream(&mut self, headers: &[EncodableHeader], end_stream: bool) -> Result<u32, ConnectionError> {
+        self.check_open()?;
+        if self.goaway_received {
+            return Err(ConnectionError::Closed);
+        }
+        if self.role != Role::Client {
+            return Err(ConnectionError::NotClient);
+        }
+
+        let open = self.streams.keys().filter(|x| self.is_local(**x)).count();
+        let limit = self.settings.remote().max_concurrent_streams.map_or(usize::MAX, |x| x as usize);
+        if open >= limit || self.next_stream_id > MAX_STREAM_ID {
+            return Err(ConnectionError::StreamLimit);
+        }
+
+        let stream_id = self.next_stream_id;
+        self.next_stream_id += 2;
+        self.streams.insert(stream_id, Stream {
+            local_closed: end_stream,
+            remote_closed: false,
+            send_window: self.settings.remote().initial_window_size as i64,
+        });
+        self.write_headers(stream_id, headers, end_stream);
+        Ok(stream_id)
+    }
+
+    // responses and trailers
+    pub fn send_headers(&mut self, stream_id: u32, headers: &[EncodableHeader], end_stream: bool) -> Result<(), ConnectionError> {
+        self.check_open()?;
+        let stream = self.sendable(stream_id)?;
+        stream.local_closed = end_stream;
+
+        self.write_headers(stream_id, headers, end_stream);
+        self.close_if_done(stream_id);
+        Ok(())
+    }
+
+    // Sends as much of data as the flow control windows allow and returns how
+    // much that was. END_STREAM is only set once all of it is sent.
+    pub fn send_data(&mut self, stream_id: u32, data: &[u8], end_stream: bool) -> Result<usize, ConnectionError> {
+        self.check_open()?;
+        let window = self.send_window;
+        let stream = self.sendable(stream_id)?;
+
+        let length = data.len().min(window.min(stream.send_window).max(0) as usize);
+        let end_stream = end_stream && length == data.len();
+        if length == 0 && !end_stream {
+            return Ok(0);
+        }
+
+        stream.send_window -= length as i64;
+        stream.local_closed = end_stream;
+        self.send_window -= length as i64;
+
+        let max_frame_size = self.settings.remote().max_frame_size as usize;
+        let mut chunks = data[..length].chunks(max_frame_size).peekable();
+        // an empty frame still carries END_STREAM
+        if chunks.peek().is_none() {
+            self.write(&Frame::Data(Data {stream_id, data: Vec::new(), end_stream, padding: None}));
+        }
+        while let Some(chunk) = chunks.next() {
+            let end_stream = end_stream && chunks.peek().is_none();
+            self.write(&Frame::Data(Data {stream_id, data: chunk.to_vec(), end_stream, padding: None}));
+        }
+
+        self.close_if_done(stream_id);
+        Ok(length)
+    }
+
+    // None for streams that are not open
+    pub fn send_window(&self, stream_id: u32) -> Option<usize> {
+        let stream = self.streams.get(&stream_id)?;
+        Some(self.send_window.min(stream.send_window).max(0) as usize)
+    }
+
+    pub fn reset(&mut self, stream_id: u32, error_code: ErrorCode) -> Result<(), ConnectionError> {
+        self.check_open()?;
+        if !self.streams.contains_key(&stream_id) {
+            return Err(ConnectionError::StreamNotOpen(stream_id));
+        }
+
+        self.reset_stream(stream_id, error_code);
+        Ok(())
+    }
+
+    // Streams the peer opens afterwards are ignored, open ones carry on.
+    pub fn go_away(&mut self, error_code: ErrorCode, debug_data: &[u8]) -> Result<(), ConnectionError> {
+        self.check_open()?;
+        self.write(&Frame::GoAway(GoAway {
+            last_stream_id: self.last_peer_stream_id,
+            error_code,
+            debug_data: debug_data.to_vec(),
+        }));
+        self.goaway_sent = true;
+        Ok(())
+    }
+
+    fn check_open(&self) -> Result<(), ConnectionError> {
+        if self.closed { Err(ConnectionError::Closed) } else { Ok(()) }
+    }
+
+    fn sendable(&mut self, stream_id: u32) -> Result<&mut Stream, ConnectionError> {
+        match self.streams.get_mut(&stream_id) {
+            Some(stream) if !stream.local_closed => Ok(stream),
+            _ => Err(ConnectionError::StreamNotOpen(stream_id)),
+        }
+    }
+
+    fn fail(&mut self, e: &ConnectionError) {
+        self.closed = true;
+        // the peer would not understand a GOAWAY
+        if matches!(e, ConnectionError::Preface(PrefaceError::Http11 | PrefaceError::InvalidPreface)) {
+            return;
+        }
+
+        self.write(&Frame::GoAway(GoAway {
+            last_stream_id: self.last_peer_stream_id,
+            error_code: e.code(),
+            debug_data: e.to_string().into_bytes(),
+        }));
+    }
+
+    fn process(&mut self) -> Result<(), ConnectionError> {
+        let mut buffer = core::mem::take(&mut self.buffer);
+        let Some(mut offset) = self.handshake.receive_preface(&buffer)? else {
+            self.buffer = buffer;
+            return Ok(());
+        };
+
+        let max_frame_size = self.settings.max_receive_frame_size();
+        loop {
+            let (frame, length) = match codec::parse(&buffer[offset..], max_frame_size) {
+                Ok(Some(x)) => x,
+                Ok(None) => break,
+                Err(e) => {
+                    let Some(stream_id) = e.stream_id() else { return Err(e.into()) };
+                    // the frame is fully buffered, skip it
+                    let header = codec::FrameHeader::parse(&buffer[offset..]).unwrap();
+                    offset += HEADER_LEN + header.length as usize;
+                    self.reset_stream(stream_id, e.code());
+                    continue;
+                }
+            };
+            offset += length;
+            self.receive_frame(frame)?;
+        }
+
+        buffer.drain(..offset);
+        self.buffer = buffer;
+        Ok(())
+    }
+
+    fn receive_frame(&mut self, frame: Frame) -> Result<(), ConnectionError> {
+        if !self.handshake.is_complete() {
+            self.handshake.receive_frame(&frame)?;
+        }
+
+        match self.assembler.receive(&frame, &mut self.decoder) {
+            Ok(Some(block)) => return self.receive_headers(block),
+            Ok(None) => {}
+            Err(e) => {
+                let Some(stream_id) = e.stream_id() else { return Err(e.into()) };
+                if !self.is_local(stream_id) {
+                    self.last_peer_stream_id = self.last_peer_stream_id.max(stream_id);
+                }
+                self.reset_stream(stream_id, e.code());
+                return Ok(());
+            }
+        }
+
+        match frame {
+            Frame::Data(x) => self.receive_data(x),
+            Frame::RstStream(x) => {
+                if self.is_idle(x.stream_id) {
+                    return Err(ConnectionError::InvalidStream(x.stream_id));
+                }
+                if self.streams.remove(&x.stream_id).is_some() {
+                    self.events.push_back(Event::Reset {stream_id: x.stream_id, error_code: x.error_code});
+                }
+                Ok(())
+            }
+            Frame::Settings(x) => self.receive_settings(x),
+            Frame::Ping(x) => {
+                if !x.ack {
+                    self.write(&Frame::Ping(Ping {ack: true, payload: x.payload}));
+                }
+                Ok(())
+            }
+            Frame::GoAway(x) => {
+                self.goaway_received = true;
+                self.events.push_back(Event::GoAway {
+                    last_stream_id: x.last_stream_id,
+                    error_code: x.error_code,
+                    debug_data: x.debug_data,
+                });
+                Ok(())
+            }
+            Frame::WindowUpdate(x) => self.receive_window_update(x),
+            // header blocks went through the assembler, PRIORITY is deprecated
+            Frame::Headers(_) | Frame::PushPromise(_) | Frame::Continuation(_) | Frame::Priority(_) | Frame::Unknown(_) => {
+                Ok(())
+            }
+        }
+    }
+
+    fn receive_headers(&mut self, block: HeaderBlock) -> Result<(), ConnectionError> {
+        let stream_id = block.stream_id;
+        if let Some(promised_stream_id) = block.promised_stream_id {
+            if self.role == Role::Server || !self.settings.local().enable_push {
+                return Err(ConnectionError::UnexpectedPush);
+            }
+
+            // pushed streams are not supported, the promise is refused
+            self.last_peer_stream_id = self.last_peer_stream_id.max(promised_stream_id);
+            self.write(&Frame::RstStream(RstStream {stream_id: promised_stream_id, error_code: ErrorCode::Cancel}));
+            return Ok(());
+        }
+
+        if let Some(stream) = self.streams.get_mut(&stream_id) {
+            if stream.remote_closed {
+                self.reset_stream(stream_id, ErrorCode::StreamClosed);
+                return Ok(());
+            }
+
+            stream.remote_closed = block.end_stream;
+            self.events.push_back(Event::Headers {stream_id, headers: block.headers, end_stream: block.end_stream});
+            self.close_if_done(stream_id);
+            return Ok(());
+        }
+
+        if !self.is_idle(stream_id) {
+            self.reset_stream(stream_id, ErrorCode::StreamClosed);
+            return Ok(());
+        }
+        // clients only receive streams through PUSH_PROMISE
+        if self.is_local(stream_id) || self.role == Role::Client {
+            return Err(ConnectionError::InvalidStream(stream_id));
+        }
+
+        self.last_peer_stream_id = stream_id;
+        if self.goaway_sent {
+            return Ok(());
+        }
+
+        let open = self.streams.keys().filter(|x| !self.is_local(**x)).count();
+        let limit = self.settings.local().max_concurrent_streams.map_or(usize::MAX, |x| x as usize);
+        if open >= limit {
+            self.write(&Frame::RstStream(RstStream {stream_id, error_code: ErrorCode::RefusedStream}));
+            return Ok(());
+        }
+
+        self.streams.insert(stream_id, Stream {
+            local_closed: false,
+            remote_closed: block.end_stream,
+            send_window: self.settings.remote().initial_window_size as i64,
+        });
+        self.events.push_back(Event::NewStream(stream_id));
+        self.events.push_back(Event::Headers {stream_id, headers: block.headers, end_stream: block.end_stream});
+        Ok(())
+    }
+
+    fn receive_data(&mut self, frame: Data) -> Result<(), ConnectionError> {
+        let stream_id = frame.stream_id;
+        // padding counts against the window too
+        let length = frame.data.len() + frame.padding.map_or(0, |x| x as usize + 1);
+        if length > 0 {
+            self.write(&Frame::WindowUpdate(WindowUpdate {stream_id: 0, increment: length as u32}));
+        }
+
+        let idle = self.is_idle(stream_id);
+        match self.streams.get_mut(&stream_id) {
+            Some(stream) if !stream.remote_closed => {
+                stream.remote_closed = frame.end_stream;
+                if length > 0 && !frame.end_stream {
+                    self.write(&Frame::WindowUpdate(WindowUpdate {stream_id, increment: length as u32}));
+                }
+                self.events.push_back(Event::Data {stream_id, data: frame.data, end_stream: frame.end_stream});
+                self.close_if_done(stream_id);
+                Ok(())
+            }
+            _ if idle => Err(ConnectionError::InvalidStream(stream_id)),
+            _ => {
+                self.reset_stream(stream_id, ErrorCode::StreamClosed);
+                Ok(())
+            }
+        }
+    }
+
+    fn receive_settings(&mut self, frame: Settings) -> Result<(), ConnectionError> {
+        let initial_window_size = self.settings.remote().initial_window_size;
+        if let Some(ack) = self.settings.receive(&frame, &mut self.encoder, &mut self.decoder)? {
+            self.write(&ack);
+        }
+
+        // RFC 9113 section 6.9.2, open streams follow the new initial window
+        let delta = self.settings.remote().initial_window_size as i64 - initial_window_size as i64;
+        for stream in self.streams.values_mut() {
+            stream.send_window += delta;
+            if stream.send_window > MAX_WINDOW_SIZE as i64 {
+                return Err(ConnectionError::FlowControl);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn receive_window_update(&mut self, frame: WindowUpdate) -> Result<(), ConnectionError> {
+        let stream_id = frame.stream_id;
+        if stream_id == 0 {
+            if frame.increment == 0 {
+                return Err(ConnectionError::InvalidWindowUpdate);
+            }
+            self.send_window += frame.increment as i64;
+            if self.send_window > MAX_WINDOW_SIZE as i64 {
+                return Err(ConnectionError::FlowControl);
+            }
+            return Ok(());
+        }
+
+        let Some(stream) = self.streams.get_mut(&stream_id) else {
+            if self.is_idle(stream_id) {
+                return Err(ConnectionError::InvalidStream(stream_id));
+            }
+            return Ok(());
+        };
+
+        stream.send_window += frame.increment as i64;
+        if frame.increment == 0 {
+            self.reset_stream(stream_id, ErrorCode::ProtocolError);
+        } else if stream.send_window > MAX_WINDOW_SIZE as i64 {
+            self.reset_stream(stream_id, ErrorCode::FlowControlError);
+        }
+        Ok(())
+    }
+
+    // odd streams are opened by clients, even ones by servers
+    fn is_local(&self, stream_id: u32) -> bool {
+        (stream_id % 2 == 1) == (self.role == Role::Client)
+    }
+
+    fn is_idle(&self, stream_id: u32) -> bool {
+        if self.is_local(stream_id) {
+            stream_id >= self.next_stream_id
+        } else {
+            stream_id > self.last_peer_stream_id
+        }
+    }
+
+    fn reset_stream(&mut self, stream_id: u32, error_code: ErrorCode) {
+        self.streams.remove(&stream_id);
+        self.write(&Frame::RstStream(RstStream {stream_id, error_code}));
+    }
+
+    fn close_if_done(&mut self, stream_id: u32) {
+        if self.streams.get(&stream_id).is_some_and(|x| x.local_closed && x.remote_closed) {
+            self.streams.remove(&stream_id);
+        }
+    }
+
+    fn write_headers(&mut self, stream_id: u32, headers: &[EncodableHeader], end_stream: bool) {
+        // validated when the peer sent it
+        let max_frame_size = self.settings.remote().max_frame_size;
+        let frames = header_block::emit_headers(&mut self.encoder, stream_id, headers, end_stream, max_frame_size)
+            .expect("invalid remote max frame size");
+        for frame in frames {
+            codec::serialize(&frame, &mut self.output);
+        }
+    }
+
+    fn write(&mut self, frame: &Frame) {
+        codec::serialize(frame, &mut self.output);
+    }
+}
@@ -0,0 +1,323 @@
+#[cfg(test)]
+mod connection_tests {
+    use core::time::Duration;
+    use crate::connection::{Connection, ConnectionError, Event};
+    use crate::frame::codec;
+    use crate::frame::error::{ErrorCode, FrameError};
+    use crate::frame::header_block::HeaderBlockError;
+    use crate::frame::preface::{PrefaceError, Role};
+    use crate::frame::types::*;
+    use crate::hpack::header::EncodableHeader;
+
+    fn pipe(from: &mut Connection, to: &mut Connection) {
+        let bytes = from.take_output();
+        to.receive(&bytes).unwrap();
+    }
+
+    fn events(connection: &mut Connection) -> Vec<Event> {
+        core::iter::from_fn(|| connection.poll_event()).collect()
+    }
+
+    fn frames(bytes: &[u8]) -> Vec<Frame> {
+        let mut frames = vec![];
+        let mut offset = 0;
+        while let Some((frame, length)) = codec::parse(&bytes[offset..], MAX_MAX_FRAME_SIZE).unwrap() {
+            frames.push(frame);
+            offset += length;
+        }
+        frames
+    }
+
+    fn handshake(client: &mut Connection, server: &mut Connection) {
+        pipe(client, server);
+        pipe(server, client);
+        pipe(client, server);
+        assert!(client.is_ready());
+        assert!(server.is_ready());
+    }
+
+    fn connected() -> (Connection, Connection) {
+        let mut client = Connection::new(Role::Client, Duration::ZERO);
+        let mut server = Connection::new(Role::Server, Duration::ZERO);
+        handshake(&mut client, &mut server);
+        (client, server)
+    }
+
+    fn request() -> Vec<EncodableHeader> {
+        vec![
+            EncodableHeader::new(":method", "GET", false),
+            EncodableHeader::new(":scheme", "https", false),
+            EncodableHeader::new(":path", "/", false),
+            EncodableHeader::new(":authority", "example.com", false),
+        ]
+    }
+
+    #[test]
+    fn test_handshake() {
+        let mut client = Connection::builder(Role::Client)
+            .settings(vec![Setting::EnablePush(0)])
+            .build(Duration::ZERO)
+            .unwrap();
+        let mut server = Connection::builder(Role::Server)
+            .settings(vec![Setting::MaxConcurrentStreams(10), Setting::HeaderTableSize(0)])
+            .build(Duration::ZERO)
+            .unwrap();
+
+        assert!(!client.is_ready());
+        handshake(&mut client, &mut server);
+        assert_eq!(client.settings().remote().max_concurrent_streams, Some(10));
+        assert!(!server.settings().remote().enable_push);
+        assert_eq!(client.encoder().header_table_size(), 0);
+        assert_eq!(server.decoder().max_header_table_size(), 0);
+        assert_eq!(client.deadline(), None);
+        assert!(events(&mut client).is_empty());
+
+        // the first block confirms the server's smaller table
+        client.open_stream(&request(), true).unwrap();
+        pipe(&mut client, &mut server);
+        assert_eq!(events(&mut server).len(), 2);
+    }
+
+    #[test]
+    fn test_request_response() {
+        let (mut client, mut server) = connected();
+
+        let stream_id = client.open_stream(&request(), true).unwrap();
+        assert_eq!(stream_id, 1);
+        pipe(&mut client, &mut server);
+        assert_eq!(events(&mut server), [
+            Event::NewStream(1),
+            Event::Headers {stream_id: 1, headers: request(), end_stream: true},
+        ]);
+
+        let response = [EncodableHeader::new(":status", "200", false)];
+        server.send_headers(1, &response, false).unwrap();
+        assert_eq!(server.send_data(1, b"hello", true), Ok(5));
+        pipe(&mut server, &mut client);
+        assert_eq!(events(&mut client), [
+            Event::Headers {stream_id: 1, headers: response.to_vec(), end_stream: false},
+            Event::Data {stream_id: 1, data: b"hello".to_vec(), end_stream: true},
+        ]);
+
+        // closed on both sides
+        assert_eq!(client.send_window(1), None);
+        assert_eq!(server.send_data(1, b"", true), Err(ConnectionError::StreamNotOpen(1)));
+        assert_eq!(client.open_stream(&request(), true), Ok(3));
+    }
+
+    #[test]
+    fn test_continuation() {
+        let (mut client, mut server) = connected();
+        let mut headers = request();
+        headers.push(EncodableHeader::new("cookie", "a".repeat(40_000), false));
+
+        client.open_stream(&headers, true).unwrap();
+        let bytes = client.take_output();
+        let kinds: Vec<FrameKind> = frames(&bytes).iter().map(|x| x.kind()).collect();
+        assert_eq!(kinds, [FrameKind::Headers, FrameKind::Continuation, FrameKind::Continuation]);
+
+        server.receive(&bytes).unwrap();
+        assert_eq!(events(&mut server)[1], Event::Headers {stream_id: 1, headers, end_stream: true});
+    }
+
+    #[test]
+    fn test_flow_control() {
+        let mut client = Connection::new(Role::Client, Duration::ZERO);
+        let mut server = Connection::builder(Role::Server)
+            .settings(vec![Setting::InitialWindowSize(10)])
+            .build(Duration::ZERO)
+            .unwrap();
+        handshake(&mut client, &mut server);
+
+        client.open_stream(&request(), false).unwrap();
+        assert_eq!(client.send_window(1), Some(10));
+        let data = [7; 25];
+        assert_eq!(client.send_data(1, &data, true), Ok(10));
+        assert_eq!(client.send_data(1, &data[10..], true), Ok(0));
+
+        // the server hands the window back as soon as the data arrived
+        pipe(&mut client, &mut server);
+        assert_eq!(events(&mut server)[2], Event::Data {stream_id: 1, data: data[..10].to_vec(), end_stream: false});
+        pipe(&mut server, &mut client);
+        assert_eq!(client.send_window(1), Some(10));
+
+        // a larger initial window applies to open streams
+        server.update_settings(vec![Setting::InitialWindowSize(20)]).unwrap();
+        pipe(&mut server, &mut client);
+        assert_eq!(client.send_window(1), Some(20));
+        assert_eq!(client.send_data(1, &data[10..], true), Ok(15));
+        pipe(&mut client, &mut server);
+        assert_eq!(events(&mut server), [Event::Data {stream_id: 1, data: data[10..].to_vec(), end_stream: true}]);
+    }
+
+    #[test]
+    fn test_reset_and_goaway() {
+        let (mut client, mut server) = connected();
+        client.open_stream(&request(), false).unwrap();
+        client.reset(1, ErrorCode::Cancel).unwrap();
+        pipe(&mut client, &mut server);
+        assert_eq!(events(&mut server)[2], Event::Reset {stream_id: 1, error_code: ErrorCode::Cancel});
+        assert_eq!(client.reset(1, ErrorCode::Cancel), Err(ConnectionError::StreamNotOpen(1)));
+
+        server.go_away(ErrorCode::NoError, b"bye").unwrap();
+        pipe(&mut server, &mut client);
+        assert_eq!(events(&mut client), [
+            Event::GoAway {last_stream_id: 1, error_code: ErrorCode::NoError, debug_data: b"bye".to_vec()},
+        ]);
+        assert_eq!(client.open_stream(&request(), true), Err(ConnectionError::Closed));
+        assert_eq!(server.open_stream(&request(), true), Err(ConnectionError::NotClient));
+    }
+
+    #[test]
+    fn test_ping_and_refused_stream() {
+        let mut client = Connection::new(Role::Client, Duration::ZERO);
+        let mut server = Connection::builder(Role::Server)
+            .settings(vec![Setting::MaxConcurrentStreams(1)])
+            .build(Duration::ZERO)
+            .unwrap();
+        handshake(&mut client, &mut server);
+
+        let mut bytes = vec![];
+        codec::serialize(&Frame::Ping(Ping {ack: false, payload: [1; 8]}), &mut bytes);
+        server.receive(&bytes).unwrap();
+        assert_eq!(frames(&server.take_output()), [Frame::Ping(Ping {ack: true, payload: [1; 8]})]);
+
+        client.open_stream(&request(), false).unwrap();
+        assert_eq!(client.open_stream(&request(), false), Err(ConnectionError::StreamLimit));
+        pipe(&mut client, &mut server);
+
+        // a client ignoring the limit
+        let mut bytes = vec![];
+        let headers = Headers {stream_id: 3, header_block: vec![], end_stream: true, end_headers: true, dependency: None, padding: None};
+        codec::serialize(&Frame::Headers(headers), &mut bytes);
+        server.receive(&bytes).unwrap();
+        assert_eq!(frames(&server.take_output()), [
+            Frame::RstStream(RstStream {stream_id: 3, error_code: ErrorCode::RefusedStream}),
+        ]);
+        assert_eq!(events(&mut server).len(), 2);
+    }
+
+    #[test]
+    fn test_lowered_max_frame_size() {
+        let mut client = Connection::new(Role::Client, Duration::ZERO);
+        let mut server = Connection::builder(Role::Server)
+            .settings(vec![Setting::MaxFrameSize(32_768)])
+            .build(Duration::ZERO)
+            .unwrap();
+        handshake(&mut client, &mut server);
+        client.open_stream(&request(), false).unwrap();
+        pipe(&mut client, &mut server);
+
+        // the client has not seen the lower size yet
+        server.update_settings(vec![Setting::MaxFrameSize(16_384)]).unwrap();
+        client.send_data(1, &[0; 20_000], false).unwrap();
+        pipe(&mut client, &mut server);
+        assert!(!server.is_closed());
+
+        pipe(&mut server, &mut client);
+        pipe(&mut client, &mut server);
+        let mut bytes = vec![];
+        codec::serialize(&Frame::Data(Data {stream_id: 1, data: vec![0; 20_000], end_stream: false, padding: None}), &mut bytes);
+        assert_eq!(server.receive(&bytes), Err(ConnectionError::Frame(FrameError::TooLarge(20_000))));
+        assert!(server.is_closed());
+    }
+
+    #[test]
+    fn test_invalid_priority() {
+        let (mut client, mut server) = connected();
+        client.open_stream(&request(), false).unwrap();
+        pipe(&mut client, &mut server);
+        assert_eq!(events(&mut server).len(), 2);
+
+        // a 4 octet PRIORITY only resets its stream, later frames still count
+        let mut bytes = vec![0, 0, 4, 2, 0, 0, 0, 0, 1, 0, 0, 0, 0];
+        codec::serialize(&Frame::Ping(Ping {ack: false, payload: [2; 8]}), &mut bytes);
+        server.receive(&bytes).unwrap();
+        assert_eq!(frames(&server.take_output()), [
+            Frame::RstStream(RstStream {stream_id: 1, error_code: ErrorCode::FrameSizeError}),
+            Frame::Ping(Ping {ack: true, payload: [2; 8]}),
+        ]);
+        assert!(!server.is_closed());
+        assert_eq!(server.send_data(1, b"x", false), Err(ConnectionError::StreamNotOpen(1)));
+    }
+
+    #[test]
+    fn test_http11() {
+        let mut server = Connection::new(Role::Server, Duration::ZERO);
+        server.take_output();
+
+        let e = server.receive(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap_err();
+        assert_eq!(e, ConnectionError::Preface(PrefaceError::Http11));
+        assert!(server.take_output().is_empty());
+        assert!(server.is_closed());
+        assert_eq!(server.receive(b""), Err(ConnectionError::Closed));
+    }
+
+    #[test]
+    fn test_http11_response() {
+        let mut client = Connection::new(Role::Client, Duration::ZERO);
+        client.take_output();
+
+        // not enough to tell yet
+        client.receive(b"HTTP").unwrap();
+        let e = client.receive(b"/1.1 400 Bad Request\r\n\r\n").unwrap_err();
+        assert_eq!(e, ConnectionError::Preface(PrefaceError::Http11));
+        assert!(client.take_output().is_empty());
+        assert!(client.is_closed());
+    }
+
+    #[test]
+    fn test_connection_error() {
+        let (_, mut server) = connected();
+
+        let mut bytes = vec![];
+        codec::serialize(&Frame::Data(Data {stream_id: 3, data: vec![], end_stream: true, padding: None}), &mut bytes);
+        assert_eq!(server.receive(&bytes), Err(ConnectionError::InvalidStream(3)));
+
+        let Frame::GoAway(goaway) = &frames(&server.take_output())[0] else { panic!("expected GOAWAY") };
+        assert_eq!(goaway.error_code, ErrorCode::ProtocolError);
+        assert_eq!(goaway.debug_data, b"Frame not allowed on stream 3!");
+        assert!(server.is_closed());
+    }
+
+    #[test]
+    fn test_continuation_flood() {
+        let (_, mut server) = connected();
+
+        let mut bytes = vec![];
+        let headers = Headers {stream_id: 1, header_block: vec![0], end_stream: true, end_headers: false, dependency: None, padding: None};
+        codec::serialize(&Frame::Headers(headers), &mut bytes);
+        let continuation = Continuation {stream_id: 1, header_block: vec![0; 16_384], end_headers: false};
+        for _ in 0..3 {
+            codec::serialize(&Frame::Continuation(continuation.clone()), &mut bytes);
+        }
+        server.receive(&bytes).unwrap();
+
+        // the fourth one takes the block past 64 KiB
+        bytes.clear();
+        codec::serialize(&Frame::Continuation(continuation), &mut bytes);
+        let e = server.receive(&bytes).unwrap_err();
+        assert_eq!(e, ConnectionError::HeaderBlock(HeaderBlockError::TooLarge));
+
+        let Frame::GoAway(goaway) = &frames(&server.take_output())[0] else { panic!("expected GOAWAY") };
+        assert_eq!(goaway.error_code, ErrorCode::EnhanceYourCalm);
+        assert!(server.is_closed());
+    }
+
+    #[test]
+    fn test_settings_timeout() {
+        let mut client = Connection::builder(Role::Client)
+            .settings_timeout(Duration::from_secs(1))
+            .build(Duration::from_secs(5))
+            .unwrap();
+        client.take_output();
+
+        assert_eq!(client.deadline(), Some(Duration::from_secs(6)));
+        assert_eq!(client.check_timeout(Duration::from_secs(5)), Ok(()));
+        let e = client.check_timeout(Duration::from_secs(6)).unwrap_err();
+        assert_eq!(e, ConnectionError::Preface(PrefaceError::SettingsTimeout));
+
+        let Frame::GoAway(goaway) = &frames(&client.take_output())[0] else { panic!("expected GOAWAY") };
+        assert_eq!(goaway.error_code, ErrorCode::SettingsTimeout);
+    }
+}
@@ -0,0 +1,9 @@
+pub mod codec;
+pub mod error;
+pub mod header_block;
+pub mod preface;
+pub mod settings;
+pub mod types;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,271 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::frame::error::FrameError;
+use crate::frame::types::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub length: u32,
+    pub kind: FrameKind,
+    pub flags: u8,
+    pub stream_id: u32,
+}
+
+impl FrameHeader {
+
+    // None if fewer than HEADER_LEN bytes are buffered
+    pub fn parse(data: &[u8]) -> Option<FrameHeader> {
+        let header = data.get(0..HEADER_LEN)?;
+        Some(FrameHeader {
+            length: u32::from_be_bytes([0, header[0], header[1], header[2]]),
+            kind: header[3].into(),
+            flags: header[4],
+            // the reserved bit is ignored
+            stream_id: read_u32(&header[5..9]) & MAX_STREAM_ID,
+        })
+    }
+
+    pub fn serialize(&self, dst: &mut Vec<u8>) {
+        dst.extend_from_slice(&self.length.to_be_bytes()[1..4]);
+        dst.push(self.kind.into());
+        dst.push(self.flags);
+        dst.extend_from_slice(&(self.stream_id & MAX_STREAM_ID).to_be_bytes());
+    }
+}
+
+// Parses the frame at the start of data, None until all of it is buffered.
+// Oversized frames are rejected as soon as their header is available.
+pub fn parse(data: &[u8], max_frame_size: u32) -> Result<Option<(Frame, usize)>, FrameError> {
+    let Some(header) = FrameHeader::parse(data) else { return Ok(None) };
+    if header.length > max_frame_size {
+        return Err(FrameError::TooLarge(header.length));
+    }
+
+    let end = HEADER_LEN + header.length as usize;
+    let Some(payload) = data.get(HEADER_LEN..end) else { return Ok(None) };
+
+    let stream_frame = matches!(
+        header.kind,
+        FrameKind::Data | FrameKind::Headers | FrameKind::Priority
+            | FrameKind::RstStream | FrameKind::PushPromise | FrameKind::Continuation
+    );
+    let connection_frame = matches!(header.kind, FrameKind::Settings | FrameKind::Ping | FrameKind::GoAway);
+    if (stream_frame && header.stream_id == 0) || (connection_frame && header.stream_id != 0) {
+        return Err(FrameError::InvalidStreamId);
+    }
+
+    let stream_id = header.stream_id;
+    let flags = header.flags;
+    let frame = match header.kind {
+        FrameKind::Data => {
+            let (data, padding) = strip_padding(flags, payload)?;
+            Frame::Data(Data {stream_id, data: data.to_vec(), end_stream: flags & END_STREAM != 0, padding})
+        }
+        FrameKind::Headers => {
+            let (mut block, padding) = strip_padding(flags, payload)?;
+            let mut dependency = None;
+            if flags & PRIORITY != 0 {
+                // RFC 9113 section 6.2: padding that eats into the priority fields
+                // is a protocol error, a payload too short for them a size error
+                let error = if block.len() + padding.unwrap_or(0) as usize >= 5 {
+                    FrameError::InvalidPadding
+                } else {
+                    FrameError::InvalidLength
+                };
+                dependency = Some(parse_dependency(block.get(0..5).ok_or(error)?));
+                block = &block[5..];
+            }
+
+            Frame::Headers(Headers {
+                stream_id,
+                header_block: block.to_vec(),
+                end_stream: flags & END_STREAM != 0,
+                end_headers: flags & END_HEADERS != 0,
+                dependency,
+                padding,
+            })
+        }
+        FrameKind::Priority => {
+            if payload.len() != 5 {
+                return Err(FrameError::InvalidPriorityLength(stream_id));
+            }
+            Frame::Priority(Priority {stream_id, dependency: parse_dependency(payload)})
+        }
+        FrameKind::RstStream => {
+            if payload.len() != 4 {
+                return Err(FrameError::InvalidLength);
+            }
+            Frame::RstStream(RstStream {stream_id, error_code: read_u32(payload).into()})
+        }
+        FrameKind::Settings => {
+            let ack = flags & ACK != 0;
+            if (ack && !payload.is_empty()) || payload.len() % 6 != 0 {
+                return Err(FrameError::InvalidLength);
+            }
+
+            let params = payload.chunks(6)
+                .map(|x| Setting::new(u16::from_be_bytes([x[0], x[1]]), read_u32(&x[2..6])))
+                .collect();
+            Frame::Settings(Settings {ack, params})
+        }
+        FrameKind::PushPromise => {
+            let (block, padding) = strip_padding(flags, payload)?;
+            let promised = block.get(0..4).ok_or(FrameError::InvalidLength)?;
+            Frame::PushPromise(PushPromise {
+                stream_id,
+                promised_stream_id: read_u32(promised) & MAX_STREAM_ID,
+                header_block: block[4..].to_vec(),
+                end_headers: flags & END_HEADERS != 0,
+                padding,
+            })
+        }
+        FrameKind::Ping => {
+            let payload: [u8; 8] = payload.try_into().map_err(|_| FrameError::InvalidLength)?;
+            Frame::Ping(Ping {ack: flags & ACK != 0, payload})
+        }
+        FrameKind::GoAway => {
+            if payload.len() < 8 {
+                return Err(FrameError::InvalidLength);
+            }
+            Frame::GoAway(GoAway {
+                last_stream_id: read_u32(&payload[0..4]) & MAX_STREAM_ID,
+                error_code: read_u32(&payload[4..8]).into(),
+                debug_data: payload[8..].to_vec(),
+            })
+        }
+        FrameKind::WindowUpdate => {
+            if payload.len() != 4 {
+                return Err(FrameError::InvalidLength);
+            }
+            Frame::WindowUpdate(WindowUpdate {stream_id, increment: read_u32(payload) & MAX_STREAM_ID})
+        }
+        FrameKind::Continuation => {
+            Frame::Continuation(Continuation {
+                stream_id,
+                header_block: payload.to_vec(),
+                end_headers: flags & END_HEADERS != 0,
+            })
+        }
+        FrameKind::Unknown(kind) => {
+            Frame::Unknown(Unknown {kind, flags, stream_id, payload: payload.to_vec()})
+        }
+    };
+
+    Ok(Some((frame, end)))
+}
+
+// Appends the frame to dst. Frame size limits are up to the caller.
+pub fn serialize(frame: &Frame, dst: &mut Vec<u8>) {
+    let mut flags: u8 = 0;
+    let mut payload: Vec<u8> = vec![];
+
+    match frame {
+        Frame::Data(x) => {
+            if x.end_stream { flags |= END_STREAM; }
+            flags |= write_padded(&mut payload, x.padding, &x.data);
+        }
+        Frame::Headers(x) => {
+            if x.end_stream { flags |= END_STREAM; }
+            if x.end_headers { flags |= END_HEADERS; }
+
+            let mut block = vec![];
+            if let Some(dependency) = &x.dependency {
+                flags |= PRIORITY;
+                write_dependency(&mut block, dependency);
+            }
+            block.extend_from_slice(&x.header_block);
+            flags |= write_padded(&mut payload, x.padding, &block);
+        }
+        Frame::Priority(x) => write_dependency(&mut payload, &x.dependency),
+        Frame::RstStream(x) => payload.extend_from_slice(&u32::from(x.error_code).to_be_bytes()),
+        Frame::Settings(x) => {
+            if x.ack { flags |= ACK; }
+            for param in &x.params {
+                payload.extend_from_slice(&param.id().to_be_bytes());
+                payload.extend_from_slice(&param.value().to_be_bytes());
+            }
+        }
+        Frame::PushPromise(x) => {
+            if x.end_headers { flags |= END_HEADERS; }
+
+            let mut block = (x.promised_stream_id & MAX_STREAM_ID).to_be_bytes().to_vec();
+            block.extend_from_slice(&x.header_block);
+            flags |= write_padded(&mut payload, x.padding, &block);
+        }
+        Frame::Ping(x) => {
+            if x.ack { flags |= ACK; }
+            payload.extend_from_slice(&x.payload);
+        }
+        Frame::GoAway(x) => {
+            payload.extend_from_slice(&(x.last_stream_id & MAX_STREAM_ID).to_be_bytes());
+            payload.extend_from_slice(&u32::from(x.error_code).to_be_bytes());
+            payload.extend_from_slice(&x.debug_data);
+        }
+        Frame::WindowUpdate(x) => payload.extend_from_slice(&(x.increment & MAX_STREAM_ID).to_be_bytes()),
+        Frame::Continuation(x) => {
+            if x.end_headers { flags |= END_HEADERS; }
+            payload.extend_from_slice(&x.header_block);
+        }
+        Frame::Unknown(x) => {
+            flags = x.flags;
+            payload.extend_from_slice(&x.payload);
+        }
+    }
+
+    let header = FrameHeader {
+        length: payload.len() as u32,
+        kind: frame.kind(),
+        flags,
+        stream_id: frame.stream_id(),
+    };
+    header.serialize(dst);
+    dst.append(&mut payload);
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
+// RFC 9113 section 6.1: returns the payload without pad length and padding
+fn strip_padding(flags: u8, payload: &[u8]) -> Result<(&[u8], Option<u8>), FrameError> {
+    if flags & PADDED == 0 {
+        return Ok((payload, None));
+    }
+
+    let pad_length = *payload.first().ok_or(FrameError::InvalidLength)?;
+    if pad_length as usize >= payload.len() {
+        return Err(FrameError::InvalidPadding);
+    }
+
+    Ok((&payload[1..payload.len() - pad_length as usize], Some(pad_length)))
+}
+
+fn write_padded(dst: &mut Vec<u8>, padding: Option<u8>, data: &[u8]) -> u8 {
+    let Some(pad_length) = padding else {
+        dst.extend_from_slice(data);
+        return 0;
+    };
+
+    dst.push(pad_length);
+    dst.extend_from_slice(data);
+    dst.resize(dst.len() + pad_length as usize, 0);
+    PADDED
+}
+
+fn parse_dependency(data: &[u8]) -> StreamDependency {
+    let value = read_u32(&data[0..4]);
+    StreamDependency {
+        stream_id: value & MAX_STREAM_ID,
+        exclusive: value & !MAX_STREAM_ID != 0,
+        weight: data[4],
+    }
+}
+
+fn write_dependency(dst: &mut Vec<u8>, dependency: &StreamDependency) {
+    let mut value = dependency.stream_id & MAX_STREAM_ID;
+    if dependency.exclusive {
+        value |= !MAX_STREAM_ID;
+    }
+    dst.extend_from_slice(&value.to_be_bytes());
+    dst.push(dependency.weight);
+}
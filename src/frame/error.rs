@@ -0,0 +1,139 @@
+use core::fmt;
+
+// RFC 9113 section 7
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    NoError,
+    ProtocolError,
+    InternalError,
+    FlowControlError,
+    SettingsTimeout,
+    StreamClosed,
+    FrameSizeError,
+    RefusedStream,
+    Cancel,
+    CompressionError,
+    ConnectError,
+    EnhanceYourCalm,
+    InadequateSecurity,
+    Http11Required,
+    // unknown codes must not trigger special behavior
+    Unknown(u32),
+}
+
+impl From<u32> for ErrorCode {
+    fn from(code: u32) -> Self {
+        match code {
+            0x0 => ErrorCode::NoError,
+            0x1 => ErrorCode::ProtocolError,
+            0x2 => ErrorCode::InternalError,
+            0x3 => ErrorCode::FlowControlError,
+            0x4 => ErrorCode::SettingsTimeout,
+            0x5 => ErrorCode::StreamClosed,
+            0x6 => ErrorCode::FrameSizeError,
+            0x7 => ErrorCode::RefusedStream,
+            0x8 => ErrorCode::Cancel,
+            0x9 => ErrorCode::CompressionError,
+            0xa => ErrorCode::ConnectError,
+            0xb => ErrorCode::EnhanceYourCalm,
+            0xc => ErrorCode::InadequateSecurity,
+            0xd => ErrorCode::Http11Required,
+            x => ErrorCode::Unknown(x),
+        }
+    }
+}
+
+impl From<ErrorCode> for u32 {
+    fn from(code: ErrorCode) -> Self {
+        match code {
+            ErrorCode::NoError => 0x0,
+            ErrorCode::ProtocolError => 0x1,
+            ErrorCode::InternalError => 0x2,
+            ErrorCode::FlowControlError => 0x3,
+            ErrorCode::SettingsTimeout => 0x4,
+            ErrorCode::StreamClosed => 0x5,
+            ErrorCode::FrameSizeError => 0x6,
+            ErrorCode::RefusedStream => 0x7,
+            ErrorCode::Cancel => 0x8,
+            ErrorCode::CompressionError => 0x9,
+            ErrorCode::ConnectError => 0xa,
+            ErrorCode::EnhanceYourCalm => 0xb,
+            ErrorCode::InadequateSecurity => 0xc,
+            ErrorCode::Http11Required => 0xd,
+            ErrorCode::Unknown(x) => x,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorCode::NoError => "NO_ERROR",
+            ErrorCode::ProtocolError => "PROTOCOL_ERROR",
+            ErrorCode::InternalError => "INTERNAL_ERROR",
+            ErrorCode::FlowControlError => "FLOW_CONTROL_ERROR",
+            ErrorCode::SettingsTimeout => "SETTINGS_TIMEOUT",
+            ErrorCode::StreamClosed => "STREAM_CLOSED",
+            ErrorCode::FrameSizeError => "FRAME_SIZE_ERROR",
+            ErrorCode::RefusedStream => "REFUSED_STREAM",
+            ErrorCode::Cancel => "CANCEL",
+            ErrorCode::CompressionError => "COMPRESSION_ERROR",
+            ErrorCode::ConnectError => "CONNECT_ERROR",
+            ErrorCode::EnhanceYourCalm => "ENHANCE_YOUR_CALM",
+            ErrorCode::InadequateSecurity => "INADEQUATE_SECURITY",
+            ErrorCode::Http11Required => "HTTP_1_1_REQUIRED",
+            ErrorCode::Unknown(x) => return write!(f, "UNKNOWN({:#x})", x),
+        };
+        f.write_str(name)
+    }
+}
+
+// Frames that can not be parsed, connection errors unless stream_id says otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    // length above SETTINGS_MAX_FRAME_SIZE
+    TooLarge(u32),
+    // length not allowed for the frame type
+    InvalidLength,
+    // PRIORITY payload other than 5 octets on the stream, RFC 9113 section 6.3
+    InvalidPriorityLength(u32),
+    // padding as long as or longer than the payload
+    InvalidPadding,
+    // stream 0 for a stream frame or a stream for a connection frame
+    InvalidStreamId,
+}
+
+impl FrameError {
+
+    // error code for the GOAWAY frame
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            FrameError::TooLarge(_) | FrameError::InvalidLength | FrameError::InvalidPriorityLength(_) => {
+                ErrorCode::FrameSizeError
+            }
+            FrameError::InvalidPadding | FrameError::InvalidStreamId => ErrorCode::ProtocolError,
+        }
+    }
+
+    // Stream errors only reset the stream, the rest of the connection is fine.
+    pub fn stream_id(&self) -> Option<u32> {
+        match self {
+            FrameError::InvalidPriorityLength(x) => Some(*x),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::TooLarge(x) => write!(f, "Frame length {} exceeds maximum!", x),
+            FrameError::InvalidLength => f.write_str("Invalid frame length!"),
+            FrameError::InvalidPriorityLength(x) => write!(f, "Invalid PRIORITY frame length on stream {}!", x),
+            FrameError::InvalidPadding => f.write_str("Invalid frame padding!"),
+            FrameError::InvalidStreamId => f.write_str("Invalid stream id for frame type!"),
+        }
+    }
+}
+
+impl core::error::Error for FrameError {}
@@ -0,0 +1,218 @@
+use alloc::vec::Vec;
+use core::fmt;
+use crate::frame::error::ErrorCode;
+use crate::frame::settings::{self, SettingsError};
+use crate::frame::types::{Continuation, Frame, Headers, Setting};
+use crate::hpack::decoder::{Decoder, DecoderError};
+use crate::hpack::encoder::Encoder;
+use crate::hpack::header::{EncodableHeader, Header};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderBlockError {
+    // any frame other than CONTINUATION on the same stream in the middle of a block
+    Interleaved,
+    // CONTINUATION without a preceding HEADERS or PUSH_PROMISE
+    UnexpectedContinuation,
+    // buffered fragments over the maximum block size
+    TooLarge,
+    Decoder(u32, DecoderError),
+}
+
+impl HeaderBlockError {
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            HeaderBlockError::Interleaved | HeaderBlockError::UnexpectedContinuation => ErrorCode::ProtocolError,
+            HeaderBlockError::TooLarge => ErrorCode::EnhanceYourCalm,
+            HeaderBlockError::Decoder(..) if self.stream_id().is_some() => ErrorCode::ProtocolError,
+            HeaderBlockError::Decoder(..) => ErrorCode::CompressionError,
+        }
+    }
+
+    // Stream errors only reset the stream, the dynamic table is still in sync.
+    pub fn stream_id(&self) -> Option<u32> {
+        match self {
+            HeaderBlockError::Decoder(stream_id, DecoderError::Malformed(_) | DecoderError::HeaderListTooLarge) => {
+                Some(*stream_id)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HeaderBlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderBlockError::Interleaved => f.write_str("Frame interleaved with a header block!"),
+            HeaderBlockError::UnexpectedContinuation => f.write_str("CONTINUATION without a header block!"),
+            HeaderBlockError::TooLarge => f.write_str("Header block size exceeded!"),
+            HeaderBlockError::Decoder(stream_id, e) => write!(f, "Stream {}: {}", stream_id, e),
+        }
+    }
+}
+
+impl core::error::Error for HeaderBlockError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderBlock {
+    pub stream_id: u32,
+    // set for PUSH_PROMISE
+    pub promised_stream_id: Option<u32>,
+    pub end_stream: bool,
+    pub headers: Vec<EncodableHeader>,
+}
+
+// Collects HEADERS or PUSH_PROMISE and their CONTINUATION frames into one
+// block. Padding and priority fields were already dropped by the parser.
+pub struct HeaderBlockAssembler {
+    pending: Option<HeaderBlock>,
+    fragments: Vec<u8>,
+    max_block_size: usize,
+}
+
+impl HeaderBlockAssembler {
+
+    pub fn new() -> Self {
+        Self {
+            pending: None,
+            fragments: Vec::new(),
+            max_block_size: usize::MAX,
+        }
+    }
+
+    // stream of the block waiting for CONTINUATION frames
+    pub fn pending_stream_id(&self) -> Option<u32> {
+        self.pending.as_ref().map(|x| x.stream_id)
+    }
+
+    pub fn max_block_size(&self) -> usize {
+        self.max_block_size
+    }
+
+    // bounds the encoded block, CONTINUATION frames are not limited otherwise
+    pub fn set_max_block_size(&mut self, size: usize) {
+        self.max_block_size = size;
+    }
+
+    // Takes every received frame in order. A complete block is decoded right
+    // away, since the HPACK state depends on the order blocks arrive in.
+    // Frames other than HEADERS, PUSH_PROMISE and CONTINUATION are ignored
+    // unless they interrupt a block.
+    pub fn receive(&mut self, frame: &Frame, decoder: &mut Decoder) -> Result<Option<HeaderBlock>, HeaderBlockError> {
+        if let Some(pending) = &self.pending {
+            let Frame::Continuation(continuation) = frame else { return Err(HeaderBlockError::Interleaved) };
+            if continuation.stream_id != pending.stream_id {
+                return Err(HeaderBlockError::Interleaved);
+            }
+
+            self.append(&continuation.header_block)?;
+            if !continuation.end_headers {
+                return Ok(None);
+            }
+        } else {
+            let (block, fragment, end_headers) = match frame {
+                Frame::Headers(x) => {
+                    let block = HeaderBlock {
+                        stream_id: x.stream_id,
+                        promised_stream_id: None,
+                        end_stream: x.end_stream,
+                        headers: Vec::new(),
+                    };
+                    (block, &x.header_block, x.end_headers)
+                }
+                Frame::PushPromise(x) => {
+                    let block = HeaderBlock {
+                        stream_id: x.stream_id,
+                        promised_stream_id: Some(x.promised_stream_id),
+                        end_stream: false,
+                        headers: Vec::new(),
+                    };
+                    (block, &x.header_block, x.end_headers)
+                }
+                Frame::Continuation(_) => return Err(HeaderBlockError::UnexpectedContinuation),
+                _ => return Ok(None),
+            };
+
+            self.pending = Some(block);
+            self.append(fragment)?;
+            if !end_headers {
+                return Ok(None);
+            }
+        }
+
+        let mut block = self.pending.take().unwrap();
+        let fragments = core::mem::take(&mut self.fragments);
+        block.headers = decoder.decode_headers(&fragments).map_err(|e| HeaderBlockError::Decoder(block.stream_id, e))?;
+        Ok(Some(block))
+    }
+
+    fn append(&mut self, fragment: &[u8]) -> Result<(), HeaderBlockError> {
+        if self.fragments.len() + fragment.len() > self.max_block_size {
+            return Err(HeaderBlockError::TooLarge);
+        }
+
+        self.fragments.extend_from_slice(fragment);
+        Ok(())
+    }
+}
+
+impl Default for HeaderBlockAssembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Encodes the header list into a HEADERS frame followed by as many
+// CONTINUATION frames as the peer's max frame size requires.
+pub fn emit(
+    encoder: &mut Encoder,
+    stream_id: u32,
+    headers: &[Header],
+    end_stream: bool,
+    max_frame_size: u32,
+) -> Result<Vec<Frame>, SettingsError> {
+    // checked first, the encoder's table must not change for a block never sent
+    settings::validate(&Setting::MaxFrameSize(max_frame_size))?;
+    split(stream_id, &encoder.encode(headers), end_stream, max_frame_size)
+}
+
+// emit with never indexed fields
+pub fn emit_headers(
+    encoder: &mut Encoder,
+    stream_id: u32,
+    headers: &[EncodableHeader],
+    end_stream: bool,
+    max_frame_size: u32,
+) -> Result<Vec<Frame>, SettingsError> {
+    settings::validate(&Setting::MaxFrameSize(max_frame_size))?;
+    split(stream_id, &encoder.encode_headers(headers), end_stream, max_frame_size)
+}
+
+// END_STREAM goes on the HEADERS frame, END_HEADERS on the last frame.
+// max_frame_size must be a valid SETTINGS_MAX_FRAME_SIZE.
+pub fn split(stream_id: u32, block: &[u8], end_stream: bool, max_frame_size: u32) -> Result<Vec<Frame>, SettingsError> {
+    settings::validate(&Setting::MaxFrameSize(max_frame_size))?;
+
+    let mut fragments = block.chunks(max_frame_size as usize);
+    let first = fragments.next().unwrap_or(&[]);
+    let count = fragments.len();
+
+    let mut frames = Vec::with_capacity(count + 1);
+    frames.push(Frame::Headers(Headers {
+        stream_id,
+        header_block: first.to_vec(),
+        end_stream,
+        end_headers: count == 0,
+        dependency: None,
+        padding: None,
+    }));
+    for (i, fragment) in fragments.enumerate() {
+        frames.push(Frame::Continuation(Continuation {
+            stream_id,
+            header_block: fragment.to_vec(),
+            end_headers: i + 1 == count,
+        }));
+    }
+
+    Ok(frames)
+}
@@ -0,0 +1,195 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+use crate::frame::codec;
+use crate::frame::error::ErrorCode;
+use crate::frame::settings::{ConnectionSettings, SettingsError};
+use crate::frame::types::{Frame, FrameKind, Setting};
+use crate::hpack::decoder::Decoder;
+
+// RFC 9113 section 3.4, sent by the client only
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+pub const DEFAULT_SETTINGS_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefaceError {
+    // an HTTP/1.x request or response instead of the preface
+    Http11,
+    InvalidPreface,
+    // the first frame after the preface must be SETTINGS
+    ExpectedSettings(FrameKind),
+    // our SETTINGS were not acknowledged in time
+    SettingsTimeout,
+}
+
+impl PrefaceError {
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            PrefaceError::SettingsTimeout => ErrorCode::SettingsTimeout,
+            _ => ErrorCode::ProtocolError,
+        }
+    }
+}
+
+impl fmt::Display for PrefaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefaceError::Http11 => f.write_str("Peer speaks HTTP/1.1, not HTTP/2!"),
+            PrefaceError::InvalidPreface => f.write_str("Invalid connection preface!"),
+            PrefaceError::ExpectedSettings(x) => write!(f, "Expected SETTINGS, received {:?} frame!", x),
+            PrefaceError::SettingsTimeout => f.write_str("SETTINGS not acknowledged in time!"),
+        }
+    }
+}
+
+impl core::error::Error for PrefaceError {}
+
+// Returns the preface length once all of it is buffered, None while data is
+// still a prefix of it or of an HTTP/1.x request or status line.
+pub fn check_preface(data: &[u8]) -> Result<Option<usize>, PrefaceError> {
+    let length = data.len().min(PREFACE.len());
+    if data[..length] == PREFACE[..length] {
+        return Ok((length == PREFACE.len()).then_some(length));
+    }
+
+    if is_http11(data) {
+        Err(PrefaceError::Http11)
+    } else if may_become_http11(data) {
+        Ok(None)
+    } else {
+        Err(PrefaceError::InvalidPreface)
+    }
+}
+
+// "HTTP/1.1 400 Bad Request" from a server, or a request line like
+// "GET / HTTP/1.1" from a client
+fn is_http11(data: &[u8]) -> bool {
+    if data.starts_with(b"HTTP/1.") {
+        return true;
+    }
+
+    let method = data.iter().take_while(|x| x.is_ascii_uppercase()).count();
+    method > 0 && data.get(method) == Some(&b' ')
+}
+
+// "HTTP/1" or a method still missing its space, given up after as many bytes
+// as the preface would have taken
+fn may_become_http11(data: &[u8]) -> bool {
+    if b"HTTP/1.".starts_with(data) {
+        return true;
+    }
+
+    data.len() < PREFACE.len() && data.iter().all(|x| x.is_ascii_uppercase())
+}
+
+// Both sides send SETTINGS first, the client after the preface. The
+// handshake is done once the peer's SETTINGS arrived and ours were
+// acknowledged. Time is whatever monotonic clock the caller uses.
+pub struct Handshake {
+    role: Role,
+    preface_received: bool,
+    settings_received: bool,
+    // None once acknowledged
+    settings_sent_at: Option<Duration>,
+    settings_timeout: Duration,
+}
+
+impl Handshake {
+
+    pub fn new(role: Role) -> Self {
+        Self {
+            role,
+            // only clients send the magic
+            preface_received: role == Role::Client,
+            settings_received: false,
+            settings_sent_at: None,
+            settings_timeout: DEFAULT_SETTINGS_TIMEOUT,
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn settings_timeout(&self) -> Duration {
+        self.settings_timeout
+    }
+
+    pub fn set_settings_timeout(&mut self, timeout: Duration) {
+        self.settings_timeout = timeout;
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.preface_received && self.settings_received && self.settings_sent_at.is_none()
+    }
+
+    // Appends the preface, for clients, and our initial SETTINGS to dst.
+    pub fn start(
+        &mut self,
+        params: Vec<Setting>,
+        settings: &mut ConnectionSettings,
+        decoder: &mut Decoder,
+        now: Duration,
+        dst: &mut Vec<u8>,
+    ) -> Result<(), SettingsError> {
+        let frame = settings.send(params, decoder)?;
+        if self.role == Role::Client {
+            dst.extend_from_slice(PREFACE);
+        }
+        codec::serialize(&frame, dst);
+        self.settings_sent_at = Some(now);
+        Ok(())
+    }
+
+    // Consumes the client preface on the server. Returns how many bytes of
+    // data it took, frames start right after, or None until it is buffered.
+    // Clients check that the server did not answer in HTTP/1.1 instead.
+    pub fn receive_preface(&mut self, data: &[u8]) -> Result<Option<usize>, PrefaceError> {
+        if self.role == Role::Client && !self.settings_received && is_http11(data) {
+            return Err(PrefaceError::Http11);
+        }
+        if self.preface_received {
+            return Ok(Some(0));
+        }
+
+        let length = check_preface(data)?;
+        self.preface_received = length.is_some();
+        Ok(length)
+    }
+
+    // Takes every frame received until the handshake is complete.
+    pub fn receive_frame(&mut self, frame: &Frame) -> Result<(), PrefaceError> {
+        match frame {
+            Frame::Settings(x) if !self.settings_received => {
+                if x.ack {
+                    return Err(PrefaceError::ExpectedSettings(FrameKind::Settings));
+                }
+                self.settings_received = true;
+            }
+            Frame::Settings(x) if x.ack => self.settings_sent_at = None,
+            _ if !self.settings_received => return Err(PrefaceError::ExpectedSettings(frame.kind())),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    // when check_timeout fails unless the ACK arrives first
+    pub fn deadline(&self) -> Option<Duration> {
+        self.settings_sent_at.map(|x| x.saturating_add(self.settings_timeout))
+    }
+
+    pub fn check_timeout(&self, now: Duration) -> Result<(), PrefaceError> {
+        match self.deadline() {
+            Some(deadline) if now >= deadline => Err(PrefaceError::SettingsTimeout),
+            _ => Ok(()),
+        }
+    }
+}
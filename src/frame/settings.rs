@@ -0,0 +1,220 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::frame::error::ErrorCode;
+use crate::frame::types::{Frame, Setting, Settings, DEFAULT_MAX_FRAME_SIZE, MAX_MAX_FRAME_SIZE};
+use crate::hpack::decoder::Decoder;
+use crate::hpack::encoder::Encoder;
+
+pub const DEFAULT_HEADER_TABLE_SIZE: u32 = 4_096;
+pub const DEFAULT_INITIAL_WINDOW_SIZE: u32 = 65_535;
+pub const MAX_WINDOW_SIZE: u32 = 0x7fff_ffff;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsError {
+    InvalidValue(Setting),
+    // ACK without an outstanding SETTINGS frame
+    UnexpectedAck,
+}
+
+impl SettingsError {
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            SettingsError::InvalidValue(Setting::InitialWindowSize(_)) => ErrorCode::FlowControlError,
+            SettingsError::InvalidValue(_) | SettingsError::UnexpectedAck => ErrorCode::ProtocolError,
+        }
+    }
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::InvalidValue(x) => write!(f, "Invalid value {} for setting {:#x}!", x.value(), x.id()),
+            SettingsError::UnexpectedAck => f.write_str("SETTINGS ACK without pending SETTINGS!"),
+        }
+    }
+}
+
+impl core::error::Error for SettingsError {}
+
+// RFC 9113 section 6.5.2
+pub fn validate(setting: &Setting) -> Result<(), SettingsError> {
+    let valid = match *setting {
+        Setting::EnablePush(x) | Setting::EnableConnectProtocol(x) => x <= 1,
+        Setting::InitialWindowSize(x) => x <= MAX_WINDOW_SIZE,
+        Setting::MaxFrameSize(x) => (DEFAULT_MAX_FRAME_SIZE..=MAX_MAX_FRAME_SIZE).contains(&x),
+        _ => true,
+    };
+
+    if valid { Ok(()) } else { Err(SettingsError::InvalidValue(*setting)) }
+}
+
+// Values in effect for one side of the connection, None means unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SettingsValues {
+    pub header_table_size: u32,
+    pub enable_push: bool,
+    pub max_concurrent_streams: Option<u32>,
+    pub initial_window_size: u32,
+    pub max_frame_size: u32,
+    pub max_header_list_size: Option<u32>,
+    pub enable_connect_protocol: bool,
+}
+
+impl SettingsValues {
+
+    pub fn new() -> Self {
+        Self {
+            header_table_size: DEFAULT_HEADER_TABLE_SIZE,
+            enable_push: true,
+            max_concurrent_streams: None,
+            initial_window_size: DEFAULT_INITIAL_WINDOW_SIZE,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            max_header_list_size: None,
+            enable_connect_protocol: false,
+        }
+    }
+
+    // Unknown settings are ignored, the values stay unchanged on error.
+    pub fn apply(&mut self, params: &[Setting]) -> Result<(), SettingsError> {
+        params.iter().try_for_each(validate)?;
+
+        for param in params {
+            match *param {
+                Setting::HeaderTableSize(x) => self.header_table_size = x,
+                Setting::EnablePush(x) => self.enable_push = x == 1,
+                Setting::MaxConcurrentStreams(x) => self.max_concurrent_streams = Some(x),
+                Setting::InitialWindowSize(x) => self.initial_window_size = x,
+                Setting::MaxFrameSize(x) => self.max_frame_size = x,
+                Setting::MaxHeaderListSize(x) => self.max_header_list_size = Some(x),
+                Setting::EnableConnectProtocol(x) => self.enable_connect_protocol = x == 1,
+                Setting::Unknown(..) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for SettingsValues {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Tracks both sides of the SETTINGS exchange. Our values take effect once the
+// peer acknowledges them, the peer's as soon as we acknowledge theirs.
+pub struct ConnectionSettings {
+    local: SettingsValues,
+    remote: SettingsValues,
+    // sent and not yet acknowledged, ACKs arrive in order
+    pending: VecDeque<Vec<Setting>>,
+}
+
+impl ConnectionSettings {
+
+    pub fn new() -> Self {
+        Self {
+            local: SettingsValues::new(),
+            remote: SettingsValues::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    // our acknowledged values
+    pub fn local(&self) -> &SettingsValues {
+        &self.local
+    }
+
+    pub fn remote(&self) -> &SettingsValues {
+        &self.remote
+    }
+
+    // our values including the ones not acknowledged yet, the peer may use
+    // any of them already
+    pub fn advertised(&self) -> SettingsValues {
+        let mut advertised = self.local;
+        for params in &self.pending {
+            // checked when sent
+            let _ = advertised.apply(params);
+        }
+        advertised
+    }
+
+    // The largest frame the peer may send, like the decoder limits it keeps
+    // the largest value sent until every smaller one is acknowledged.
+    pub fn max_receive_frame_size(&self) -> u32 {
+        let mut values = self.local;
+        let mut max_frame_size = values.max_frame_size;
+        for params in &self.pending {
+            // checked when sent
+            let _ = values.apply(params);
+            max_frame_size = max_frame_size.max(values.max_frame_size);
+        }
+        max_frame_size
+    }
+
+    pub fn pending_acks(&self) -> usize {
+        self.pending.len()
+    }
+
+    // Returns the SETTINGS frame to send. The peer may use a larger table or
+    // header list right away, so the decoder limits are raised before the
+    // ACK and only lowered after it.
+    pub fn send(&mut self, params: Vec<Setting>, decoder: &mut Decoder) -> Result<Frame, SettingsError> {
+        let mut advertised = self.advertised();
+        advertised.apply(&params)?;
+        if advertised.header_table_size as usize > decoder.max_header_table_size() {
+            decoder.set_max_header_table_size(advertised.header_table_size as usize);
+        }
+        let max_header_list_size = limit(advertised.max_header_list_size);
+        if max_header_list_size > decoder.max_header_list_size() {
+            decoder.set_max_header_list_size(max_header_list_size);
+        }
+
+        self.pending.push_back(params.clone());
+        Ok(Frame::Settings(Settings {ack: false, params}))
+    }
+
+    // Returns the ACK to send for a peer SETTINGS frame, None for an ACK.
+    pub fn receive(
+        &mut self,
+        frame: &Settings,
+        encoder: &mut Encoder,
+        decoder: &mut Decoder,
+    ) -> Result<Option<Frame>, SettingsError> {
+        if frame.ack {
+            let params = self.pending.pop_front().ok_or(SettingsError::UnexpectedAck)?;
+            self.local.apply(&params)?;
+
+            // later pending values are still allowed
+            let advertised = self.advertised();
+            decoder.set_max_header_table_size(advertised.header_table_size as usize);
+            decoder.set_max_header_list_size(limit(advertised.max_header_list_size));
+            return Ok(None);
+        }
+
+        self.remote.apply(&frame.params)?;
+        // every change is signalled, so the peer sees the smallest size too,
+        // and later resizes stay within the peer's limit
+        for param in &frame.params {
+            if let Setting::HeaderTableSize(x) = param {
+                encoder.set_max_header_table_size(*x as usize);
+                encoder.set_header_table_size(*x as usize);
+            }
+        }
+
+        Ok(Some(Frame::Settings(Settings {ack: true, params: Vec::new()})))
+    }
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn limit(value: Option<u32>) -> usize {
+    value.map_or(usize::MAX, |x| x as usize)
+}
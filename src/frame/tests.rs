@@ -0,0 +1,674 @@
+#[cfg(test)]
+mod codec_tests {
+    use crate::frame::codec::{self, FrameHeader};
+    use crate::frame::error::{ErrorCode, FrameError};
+    use crate::frame::types::*;
+
+    fn round_trip(frame: Frame) -> Vec<u8> {
+        let mut bytes = vec![];
+        codec::serialize(&frame, &mut bytes);
+        assert_eq!(codec::parse(&bytes, DEFAULT_MAX_FRAME_SIZE), Ok(Some((frame, bytes.len()))));
+        bytes
+    }
+
+    #[test]
+    fn test_frame_header() {
+        let bytes = [0, 0, 5, 1, 0x25, 0x80, 0, 0, 3];
+        let header = FrameHeader::parse(&bytes).unwrap();
+        assert_eq!(header, FrameHeader {length: 5, kind: FrameKind::Headers, flags: 0x25, stream_id: 3});
+        assert_eq!(FrameHeader::parse(&bytes[0..8]), None);
+
+        let mut serialized = vec![];
+        header.serialize(&mut serialized);
+        assert_eq!(serialized, [0, 0, 5, 1, 0x25, 0, 0, 0, 3]);
+    }
+
+    #[test]
+    fn test_data() {
+        let bytes = round_trip(Frame::Data(Data {stream_id: 1, data: b"hello".to_vec(), end_stream: true, padding: None}));
+        assert_eq!(bytes, [0, 0, 5, 0, END_STREAM, 0, 0, 0, 1, b'h', b'e', b'l', b'l', b'o']);
+
+        let bytes = round_trip(Frame::Data(Data {stream_id: 1, data: b"hi".to_vec(), end_stream: false, padding: Some(3)}));
+        assert_eq!(bytes, [0, 0, 6, 0, PADDED, 0, 0, 0, 1, 3, b'h', b'i', 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_headers() {
+        let bytes = round_trip(Frame::Headers(Headers {
+            stream_id: 3,
+            header_block: vec![0x82, 0x84],
+            end_stream: false,
+            end_headers: true,
+            dependency: Some(StreamDependency {stream_id: 1, exclusive: true, weight: 15}),
+            padding: Some(2),
+        }));
+        assert_eq!(bytes[3..5], [1, END_HEADERS | PADDED | PRIORITY]);
+        assert_eq!(bytes[9..], [2, 0x80, 0, 0, 1, 15, 0x82, 0x84, 0, 0]);
+
+        round_trip(Frame::Headers(Headers {
+            stream_id: 3,
+            header_block: vec![0x82],
+            end_stream: true,
+            end_headers: false,
+            dependency: None,
+            padding: None,
+        }));
+    }
+
+    #[test]
+    fn test_control_frames() {
+        round_trip(Frame::Priority(Priority {
+            stream_id: 5,
+            dependency: StreamDependency {stream_id: 3, exclusive: false, weight: 255},
+        }));
+        round_trip(Frame::RstStream(RstStream {stream_id: 5, error_code: ErrorCode::Cancel}));
+        round_trip(Frame::PushPromise(PushPromise {
+            stream_id: 1,
+            promised_stream_id: 2,
+            header_block: vec![0x82],
+            end_headers: true,
+            padding: Some(1),
+        }));
+        round_trip(Frame::Ping(Ping {ack: true, payload: *b"12345678"}));
+        round_trip(Frame::GoAway(GoAway {
+            last_stream_id: 7,
+            error_code: ErrorCode::Unknown(0xff),
+            debug_data: b"bye".to_vec(),
+        }));
+        round_trip(Frame::WindowUpdate(WindowUpdate {stream_id: 0, increment: 65535}));
+        round_trip(Frame::Continuation(Continuation {stream_id: 1, header_block: vec![0x84], end_headers: true}));
+        round_trip(Frame::Unknown(Unknown {kind: 0xfa, flags: 0xff, stream_id: 9, payload: vec![1, 2, 3]}));
+    }
+
+    #[test]
+    fn test_settings() {
+        let bytes = round_trip(Frame::Settings(Settings {
+            ack: false,
+            params: vec![Setting::HeaderTableSize(0), Setting::MaxFrameSize(32768), Setting::Unknown(0x10, 1)],
+        }));
+        assert_eq!(bytes[0..9], [0, 0, 18, 4, 0, 0, 0, 0, 0]);
+        assert_eq!(bytes[9..15], [0, 1, 0, 0, 0, 0]);
+
+        let bytes = round_trip(Frame::Settings(Settings {ack: true, params: vec![]}));
+        assert_eq!(bytes, [0, 0, 0, 4, ACK, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_partial_frames() {
+        let mut bytes = vec![];
+        codec::serialize(&Frame::Ping(Ping {ack: false, payload: [0; 8]}), &mut bytes);
+        codec::serialize(&Frame::Ping(Ping {ack: true, payload: [0; 8]}), &mut bytes);
+
+        for i in 0..17 {
+            assert_eq!(codec::parse(&bytes[0..i], DEFAULT_MAX_FRAME_SIZE), Ok(None));
+        }
+
+        let (frame, length) = codec::parse(&bytes, DEFAULT_MAX_FRAME_SIZE).unwrap().unwrap();
+        assert_eq!(frame, Frame::Ping(Ping {ack: false, payload: [0; 8]}));
+        assert_eq!(length, 17);
+        let (frame, _) = codec::parse(&bytes[length..], DEFAULT_MAX_FRAME_SIZE).unwrap().unwrap();
+        assert_eq!(frame, Frame::Ping(Ping {ack: true, payload: [0; 8]}));
+    }
+
+    #[test]
+    fn test_frame_size() {
+        let mut bytes = vec![];
+        codec::serialize(&Frame::Data(Data {stream_id: 1, data: vec![0; 100], end_stream: false, padding: None}), &mut bytes);
+
+        // rejected from the header alone
+        assert_eq!(codec::parse(&bytes[0..9], 99), Err(FrameError::TooLarge(100)));
+        assert_eq!(codec::parse(&bytes, 100).unwrap().unwrap().1, 109);
+        assert_eq!(FrameError::TooLarge(100).code(), ErrorCode::FrameSizeError);
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        let cases: [(&[u8], FrameError); 11] = [
+            // DATA on stream 0
+            (&[0, 0, 0, 0, 0, 0, 0, 0, 0], FrameError::InvalidStreamId),
+            // PING on stream 1
+            (&[0, 0, 8, 6, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0], FrameError::InvalidStreamId),
+            // padding as long as the payload
+            (&[0, 0, 2, 0, PADDED, 0, 0, 0, 1, 2, 0], FrameError::InvalidPadding),
+            (&[0, 0, 0, 0, PADDED, 0, 0, 0, 1], FrameError::InvalidLength),
+            // PRIORITY only resets its stream
+            (&[0, 0, 4, 2, 0, 0, 0, 0, 1, 0, 0, 0, 0], FrameError::InvalidPriorityLength(1)),
+            (&[0, 0, 5, 4, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0], FrameError::InvalidLength),
+            // SETTINGS ACK with a payload
+            (&[0, 0, 6, 4, ACK, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0], FrameError::InvalidLength),
+            (&[0, 0, 7, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], FrameError::InvalidLength),
+            // HEADERS with PRIORITY but no room for it
+            (&[0, 0, 1, 1, PRIORITY, 0, 0, 0, 1, 0x82], FrameError::InvalidLength),
+            (&[0, 0, 2, 1, PADDED | PRIORITY, 0, 0, 0, 1, 0, 0x82], FrameError::InvalidLength),
+            // padding that leaves only 4 of the 5 priority octets
+            (&[0, 0, 7, 1, PADDED | PRIORITY, 0, 0, 0, 1, 2, 0, 0, 0, 3, 16, 0, 0], FrameError::InvalidPadding),
+        ];
+
+        for (bytes, error) in cases {
+            assert_eq!(codec::parse(bytes, DEFAULT_MAX_FRAME_SIZE), Err(error));
+        }
+        assert_eq!(FrameError::InvalidPadding.code(), ErrorCode::ProtocolError);
+        assert_eq!(FrameError::InvalidPriorityLength(1).code(), ErrorCode::FrameSizeError);
+        assert_eq!(FrameError::InvalidPriorityLength(1).stream_id(), Some(1));
+        assert_eq!(FrameError::InvalidLength.stream_id(), None);
+    }
+
+    #[test]
+    fn test_error_codes() {
+        for code in 0..=0xd {
+            assert_eq!(u32::from(ErrorCode::from(code)), code);
+            assert!(!matches!(ErrorCode::from(code), ErrorCode::Unknown(_)));
+        }
+        assert_eq!(ErrorCode::from(0xe), ErrorCode::Unknown(0xe));
+        assert_eq!(ErrorCode::Http11Required.to_string(), "HTTP_1_1_REQUIRED");
+    }
+}
+
+#[cfg(test)]
+mod header_block_tests {
+    use crate::frame::error::ErrorCode;
+    use crate::frame::header_block::{HeaderBlock, HeaderBlockAssembler, HeaderBlockError};
+    use crate::frame::types::*;
+    use crate::hpack::decoder::{Decoder, DecoderError};
+    use crate::hpack::encoder::Encoder;
+    use crate::hpack::header::{EncodableHeader, Header};
+    use crate::hpack::validation::Validation;
+
+    fn headers_frame(stream_id: u32, header_block: &[u8], end_headers: bool) -> Frame {
+        Frame::Headers(Headers {
+            stream_id,
+            header_block: header_block.to_vec(),
+            end_stream: true,
+            end_headers,
+            dependency: Some(StreamDependency {stream_id: 0, exclusive: false, weight: 15}),
+            padding: Some(4),
+        })
+    }
+
+    fn continuation(stream_id: u32, header_block: &[u8], end_headers: bool) -> Frame {
+        Frame::Continuation(Continuation {stream_id, header_block: header_block.to_vec(), end_headers})
+    }
+
+    fn request() -> Vec<Header> {
+        vec![
+            Header::new(":method", "GET"),
+            Header::new(":scheme", "https"),
+            Header::new(":path", "/"),
+            Header::new(":authority", "www.example.com"),
+        ]
+    }
+
+    #[test]
+    fn test_assemble_continuations() {
+        let block = Encoder::new().encode(&request());
+        let mut assembler = HeaderBlockAssembler::new();
+        let mut decoder = Decoder::new();
+
+        assert_eq!(assembler.receive(&headers_frame(1, &block[0..2], false), &mut decoder), Ok(None));
+        assert_eq!(assembler.pending_stream_id(), Some(1));
+        assert_eq!(assembler.receive(&continuation(1, &block[2..5], false), &mut decoder), Ok(None));
+        let assembled = assembler.receive(&continuation(1, &block[5..], true), &mut decoder).unwrap().unwrap();
+
+        let expected: Vec<EncodableHeader> = request().into_iter()
+            .map(|x| EncodableHeader::new(x.name, x.value, false))
+            .collect();
+        assert_eq!(assembled, HeaderBlock {stream_id: 1, promised_stream_id: None, end_stream: true, headers: expected});
+        assert_eq!(assembler.pending_stream_id(), None);
+        assert_eq!(decoder.header_table().len(), 1);
+    }
+
+    #[test]
+    fn test_blocks_decoded_in_order() {
+        let mut encoder = Encoder::new();
+        let first = encoder.encode(&request());
+        let second = encoder.encode(&request());
+        let mut assembler = HeaderBlockAssembler::new();
+        let mut decoder = Decoder::new();
+
+        // the second block refers to the entry added by the first
+        assembler.receive(&headers_frame(1, &first, true), &mut decoder).unwrap().unwrap();
+        let assembled = assembler.receive(&headers_frame(3, &second, true), &mut decoder).unwrap().unwrap();
+        assert_eq!(assembled.headers[3].value, "www.example.com");
+
+        let ping = Frame::Ping(Ping {ack: false, payload: [0; 8]});
+        assert_eq!(assembler.receive(&ping, &mut decoder), Ok(None));
+    }
+
+    #[test]
+    fn test_push_promise() {
+        let block = Encoder::new().encode(&request());
+        let mut assembler = HeaderBlockAssembler::new();
+        let mut decoder = Decoder::new();
+
+        let frame = Frame::PushPromise(PushPromise {
+            stream_id: 1,
+            promised_stream_id: 2,
+            header_block: block[0..1].to_vec(),
+            end_headers: false,
+            padding: None,
+        });
+        assert_eq!(assembler.receive(&frame, &mut decoder), Ok(None));
+        let assembled = assembler.receive(&continuation(1, &block[1..], true), &mut decoder).unwrap().unwrap();
+        assert_eq!(assembled.promised_stream_id, Some(2));
+        assert_eq!(assembled.headers.len(), 4);
+    }
+
+    #[test]
+    fn test_interleaved_frames() {
+        let mut decoder = Decoder::new();
+        let data = Frame::Data(Data {stream_id: 1, data: vec![], end_stream: false, padding: None});
+        let frames = [data, continuation(3, &[0x84], true), headers_frame(3, &[0x82], true)];
+
+        for frame in frames {
+            let mut assembler = HeaderBlockAssembler::new();
+            assembler.receive(&headers_frame(1, &[0x82], false), &mut decoder).unwrap();
+            let result = assembler.receive(&frame, &mut decoder);
+            assert_eq!(result, Err(HeaderBlockError::Interleaved));
+            assert_eq!(result.unwrap_err().code(), ErrorCode::ProtocolError);
+        }
+
+        let mut assembler = HeaderBlockAssembler::new();
+        let result = assembler.receive(&continuation(1, &[0x82], true), &mut decoder);
+        assert_eq!(result, Err(HeaderBlockError::UnexpectedContinuation));
+    }
+
+    #[test]
+    fn test_max_block_size() {
+        let mut assembler = HeaderBlockAssembler::new();
+        assembler.set_max_block_size(4);
+        let mut decoder = Decoder::new();
+
+        assembler.receive(&headers_frame(1, &[0x82, 0x84], false), &mut decoder).unwrap();
+        assembler.receive(&continuation(1, &[0x86, 0x87], false), &mut decoder).unwrap();
+        let result = assembler.receive(&continuation(1, &[0x88], false), &mut decoder);
+        assert_eq!(result, Err(HeaderBlockError::TooLarge));
+    }
+
+    #[test]
+    fn test_decoder_errors() {
+        let mut assembler = HeaderBlockAssembler::new();
+        let mut decoder = Decoder::new();
+
+        // truncated blocks leave the tables out of sync
+        let error = assembler.receive(&headers_frame(1, &[0x41, 0x03], true), &mut decoder).unwrap_err();
+        assert_eq!(error, HeaderBlockError::Decoder(1, DecoderError::Truncated));
+        assert_eq!(error.code(), ErrorCode::CompressionError);
+        assert_eq!(error.stream_id(), None);
+
+        // malformed header lists only fail their stream
+        decoder.set_validation(Validation::Strict);
+        let block = Encoder::new().encode(&[Header::new("Upper", "case")]);
+        let error = assembler.receive(&headers_frame(3, &block, true), &mut decoder).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::ProtocolError);
+        assert_eq!(error.stream_id(), Some(3));
+    }
+}
+
+#[cfg(test)]
+mod emit_tests {
+    use crate::frame::codec;
+    use crate::frame::header_block::{self, HeaderBlockAssembler};
+    use crate::frame::settings::SettingsError;
+    use crate::frame::types::*;
+    use crate::hpack::decoder::Decoder;
+    use crate::hpack::encoder::Encoder;
+    use crate::hpack::header::{EncodableHeader, Header};
+
+    fn headers(cookie_length: usize) -> Vec<Header> {
+        vec![
+            Header::new(":status", "200"),
+            Header::new("set-cookie", "a".repeat(cookie_length)),
+            Header::new("set-cookie", "b".repeat(cookie_length)),
+        ]
+    }
+
+    #[test]
+    fn test_emit_single_frame() {
+        let frames = header_block::emit(&mut Encoder::new(), 1, &headers(100), true, DEFAULT_MAX_FRAME_SIZE).unwrap();
+
+        assert_eq!(frames.len(), 1);
+        let Frame::Headers(frame) = &frames[0] else { panic!("expected HEADERS") };
+        assert!(frame.end_stream);
+        assert!(frame.end_headers);
+        assert_eq!(frame.stream_id, 1);
+    }
+
+    #[test]
+    fn test_emit_continuations() {
+        let mut encoder = Encoder::new();
+        let frames = header_block::emit(&mut encoder, 3, &headers(20_000), true, DEFAULT_MAX_FRAME_SIZE).unwrap();
+        let size: usize = encoder.stats().encoded_bytes as usize;
+
+        assert_eq!(frames.len(), size.div_ceil(DEFAULT_MAX_FRAME_SIZE as usize));
+        let Frame::Headers(first) = &frames[0] else { panic!("expected HEADERS") };
+        assert!(first.end_stream);
+        assert!(!first.end_headers);
+        for (i, frame) in frames[1..].iter().enumerate() {
+            let Frame::Continuation(x) = frame else { panic!("expected CONTINUATION") };
+            assert_eq!(x.stream_id, 3);
+            assert!(x.header_block.len() <= DEFAULT_MAX_FRAME_SIZE as usize);
+            assert_eq!(x.end_headers, i == frames.len() - 2);
+        }
+
+        // back through the wire format and the assembler
+        let mut bytes = vec![];
+        for frame in &frames {
+            codec::serialize(frame, &mut bytes);
+        }
+        let mut assembler = HeaderBlockAssembler::new();
+        let mut decoder = Decoder::new();
+        let mut offset = 0;
+        let mut assembled = None;
+        while let Some((frame, length)) = codec::parse(&bytes[offset..], DEFAULT_MAX_FRAME_SIZE).unwrap() {
+            offset += length;
+            assembled = assembler.receive(&frame, &mut decoder).unwrap();
+        }
+
+        let assembled = assembled.unwrap();
+        assert!(assembled.end_stream);
+        assert_eq!(assembled.headers.len(), 3);
+        assert_eq!(assembled.headers[2].value, "b".repeat(20_000));
+    }
+
+    #[test]
+    fn test_emit_sensitive_and_empty() {
+        let mut encoder = Encoder::new();
+        let frames = header_block::emit_headers(&mut encoder, 5, &[EncodableHeader::new("authorization", "x", true)], false, DEFAULT_MAX_FRAME_SIZE)
+            .unwrap();
+        let Frame::Headers(frame) = &frames[0] else { panic!("expected HEADERS") };
+        assert_eq!(frame.header_block[0] & 0xf0, 0x10);
+        assert!(!frame.end_stream);
+
+        let frames = header_block::split(7, &[], false, DEFAULT_MAX_FRAME_SIZE).unwrap();
+        assert_eq!(frames, vec![Frame::Headers(Headers {
+            stream_id: 7,
+            header_block: vec![],
+            end_stream: false,
+            end_headers: true,
+            dependency: None,
+            padding: None,
+        })]);
+    }
+
+    #[test]
+    fn test_emit_invalid_max_frame_size() {
+        for max_frame_size in [0, 1, DEFAULT_MAX_FRAME_SIZE - 1, MAX_MAX_FRAME_SIZE + 1] {
+            let error = SettingsError::InvalidValue(Setting::MaxFrameSize(max_frame_size));
+            assert_eq!(header_block::split(1, &[0x82], true, max_frame_size), Err(error));
+            assert_eq!(header_block::emit(&mut Encoder::new(), 1, &headers(1), true, max_frame_size), Err(error));
+        }
+    }
+}
+
+#[cfg(test)]
+mod settings_tests {
+    use crate::frame::error::ErrorCode;
+    use crate::frame::settings::{ConnectionSettings, SettingsError, SettingsValues};
+    use crate::frame::types::*;
+    use crate::hpack::decoder::{Decoder, DecoderError};
+    use crate::hpack::encoder::Encoder;
+    use crate::hpack::header::Header;
+
+    fn ack() -> Settings {
+        Settings {ack: true, params: vec![]}
+    }
+
+    #[test]
+    fn test_validation() {
+        let mut values = SettingsValues::new();
+        for (setting, code) in [
+            (Setting::EnablePush(2), ErrorCode::ProtocolError),
+            (Setting::InitialWindowSize(1 << 31), ErrorCode::FlowControlError),
+            (Setting::MaxFrameSize(DEFAULT_MAX_FRAME_SIZE - 1), ErrorCode::ProtocolError),
+            (Setting::MaxFrameSize(MAX_MAX_FRAME_SIZE + 1), ErrorCode::ProtocolError),
+            (Setting::EnableConnectProtocol(2), ErrorCode::ProtocolError),
+        ] {
+            let e = values.apply(&[Setting::MaxConcurrentStreams(1), setting]).unwrap_err();
+            assert_eq!(e, SettingsError::InvalidValue(setting));
+            assert_eq!(e.code(), code);
+        }
+        assert_eq!(values, SettingsValues::new());
+
+        values.apply(&[
+            Setting::EnablePush(0),
+            Setting::MaxFrameSize(MAX_MAX_FRAME_SIZE),
+            Setting::Unknown(0xff, 1),
+            Setting::MaxHeaderListSize(8192),
+        ]).unwrap();
+        assert!(!values.enable_push);
+        assert_eq!(values.max_frame_size, MAX_MAX_FRAME_SIZE);
+        assert_eq!(values.max_header_list_size, Some(8192));
+    }
+
+    #[test]
+    fn test_remote_header_table_size() {
+        let mut settings = ConnectionSettings::new();
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+
+        let frame = Settings {ack: false, params: vec![Setting::HeaderTableSize(0), Setting::HeaderTableSize(256)]};
+        let response = settings.receive(&frame, &mut encoder, &mut decoder).unwrap();
+        assert_eq!(response, Some(Frame::Settings(ack())));
+        assert_eq!(settings.remote().header_table_size, 256);
+        assert_eq!(encoder.header_table_size(), 256);
+
+        // both updates go out with the next block
+        let block = encoder.encode(&[Header::new(":method", "GET")]);
+        assert_eq!(block, [0x20, 0x3f, 0xe1, 0x01, 0x82]);
+
+        // the peer's value bounds any later resize
+        assert_eq!(encoder.max_header_table_size(), 256);
+        encoder.set_header_table_size(4096);
+        assert_eq!(encoder.header_table_size(), 256);
+        encoder.set_header_table_size(128);
+        let block = encoder.encode(&[Header::new(":method", "GET")]);
+        assert_eq!(block, [0x3f, 0x61, 0x82]);
+    }
+
+    #[test]
+    fn test_acknowledged_table_size_requires_update() {
+        let mut settings = ConnectionSettings::new();
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+
+        settings.send(vec![Setting::HeaderTableSize(0)], &mut decoder).unwrap();
+        assert_eq!(decoder.decode(&[0x82]).map(|x| x.len()), Ok(1));
+        settings.receive(&ack(), &mut encoder, &mut decoder).unwrap();
+        assert_eq!(decoder.header_table_size(), 0);
+        assert_eq!(decoder.decode(&[0x82]), Err(DecoderError::MissingTableSizeUpdate));
+        assert_eq!(decoder.decode(&[0x20, 0x82]).map(|x| x.len()), Ok(1));
+    }
+
+    #[test]
+    fn test_local_limits() {
+        let mut settings = ConnectionSettings::new();
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+
+        // raised as soon as it is advertised
+        let frame = settings.send(vec![Setting::HeaderTableSize(8192), Setting::MaxHeaderListSize(100)], &mut decoder);
+        assert_eq!(frame, Ok(Frame::Settings(Settings {
+            ack: false,
+            params: vec![Setting::HeaderTableSize(8192), Setting::MaxHeaderListSize(100)],
+        })));
+        assert_eq!(decoder.max_header_table_size(), 8192);
+        assert_eq!(decoder.max_header_list_size(), usize::MAX);
+        assert_eq!(settings.local().header_table_size, 4096);
+
+        settings.send(vec![Setting::HeaderTableSize(1024)], &mut decoder).unwrap();
+        assert_eq!(settings.pending_acks(), 2);
+        assert_eq!(decoder.max_header_table_size(), 8192);
+
+        // lowered once acknowledged
+        assert_eq!(settings.receive(&ack(), &mut encoder, &mut decoder), Ok(None));
+        assert_eq!(settings.local().header_table_size, 8192);
+        assert_eq!(decoder.max_header_list_size(), 100);
+        assert_eq!(decoder.max_header_table_size(), 1024);
+        settings.receive(&ack(), &mut encoder, &mut decoder).unwrap();
+        assert_eq!(settings.local().header_table_size, 1024);
+        assert_eq!(decoder.decode(&[0x3f, 0xe2, 0x07]), Err(DecoderError::InvalidTableSizeUpdate));
+
+        assert_eq!(settings.receive(&ack(), &mut encoder, &mut decoder), Err(SettingsError::UnexpectedAck));
+        assert_eq!(settings.send(vec![Setting::EnablePush(5)], &mut decoder), Err(SettingsError::InvalidValue(Setting::EnablePush(5))));
+        assert_eq!(settings.pending_acks(), 0);
+
+        // frames up to the largest unacknowledged size stay allowed
+        settings.send(vec![Setting::MaxFrameSize(32_768)], &mut decoder).unwrap();
+        settings.send(vec![Setting::MaxFrameSize(16_384)], &mut decoder).unwrap();
+        assert_eq!(settings.advertised().max_frame_size, 16_384);
+        assert_eq!(settings.max_receive_frame_size(), 32_768);
+        settings.receive(&ack(), &mut encoder, &mut decoder).unwrap();
+        assert_eq!(settings.max_receive_frame_size(), 32_768);
+        settings.receive(&ack(), &mut encoder, &mut decoder).unwrap();
+        assert_eq!(settings.max_receive_frame_size(), 16_384);
+    }
+}
+
+#[cfg(test)]
+mod preface_tests {
+    use core::time::Duration;
+    use crate::frame::codec;
+    use crate::frame::error::ErrorCode;
+    use crate::frame::preface::{self, Handshake, PrefaceError, Role, PREFACE};
+    use crate::frame::settings::ConnectionSettings;
+    use crate::frame::types::*;
+    use crate::hpack::decoder::Decoder;
+    use crate::hpack::encoder::Encoder;
+
+    struct Peer {
+        handshake: Handshake,
+        settings: ConnectionSettings,
+        encoder: Encoder,
+        decoder: Decoder,
+    }
+
+    impl Peer {
+        fn new(role: Role) -> Self {
+            Peer {
+                handshake: Handshake::new(role),
+                settings: ConnectionSettings::new(),
+                encoder: Encoder::new(),
+                decoder: Decoder::new(),
+            }
+        }
+
+        fn start(&mut self, params: Vec<Setting>) -> Vec<u8> {
+            let mut bytes = vec![];
+            self.handshake.start(params, &mut self.settings, &mut self.decoder, Duration::ZERO, &mut bytes).unwrap();
+            bytes
+        }
+
+        fn receive(&mut self, bytes: &[u8]) -> Vec<u8> {
+            let mut offset = self.handshake.receive_preface(bytes).unwrap().unwrap();
+            let mut response = vec![];
+            while let Some((frame, length)) = codec::parse(&bytes[offset..], DEFAULT_MAX_FRAME_SIZE).unwrap() {
+                offset += length;
+                self.handshake.receive_frame(&frame).unwrap();
+                let Frame::Settings(x) = &frame else { continue };
+                if let Some(ack) = self.settings.receive(x, &mut self.encoder, &mut self.decoder).unwrap() {
+                    codec::serialize(&ack, &mut response);
+                }
+            }
+            assert_eq!(offset, bytes.len());
+            response
+        }
+    }
+
+    #[test]
+    fn test_check_preface() {
+        assert_eq!(preface::check_preface(b""), Ok(None));
+        assert_eq!(preface::check_preface(&PREFACE[..10]), Ok(None));
+        assert_eq!(preface::check_preface(PREFACE), Ok(Some(24)));
+        assert_eq!(preface::check_preface(&[PREFACE, &[0, 0, 0]].concat()), Ok(Some(24)));
+
+        assert_eq!(preface::check_preface(b"GET / HTTP/1.1\r\nHost: a\r\n\r\n"), Err(PrefaceError::Http11));
+        assert_eq!(preface::check_preface(b"PRI * HTTP/1.1\r\n"), Err(PrefaceError::Http11));
+        assert_eq!(preface::check_preface(b"HTTP/1.1 400 Bad Request\r\n"), Err(PrefaceError::Http11));
+        assert_eq!(preface::check_preface(&[0, 0, 0, 4, 0]), Err(PrefaceError::InvalidPreface));
+        assert_eq!(preface::check_preface(b"get / HTTP/1.1\r\n"), Err(PrefaceError::InvalidPreface));
+        assert_eq!(preface::check_preface(&[b'A'; 24]), Err(PrefaceError::InvalidPreface));
+        assert_eq!(PrefaceError::Http11.code(), ErrorCode::ProtocolError);
+    }
+
+    #[test]
+    fn test_http11_byte_at_a_time() {
+        // undecided until the space after the method or the "HTTP/1." prefix
+        let lines: [(&[u8], usize); 3] = [
+            (b"GET / HTTP/1.1\r\n", 4),
+            // shares "PRI" with the preface
+            (b"PRIORITY / HTTP/1.1\r\n", 9),
+            (b"HTTP/1.1 200 OK\r\n", 7),
+        ];
+        for (line, decided) in lines {
+            for length in 1..=line.len() {
+                let expected = if length < decided { Ok(None) } else { Err(PrefaceError::Http11) };
+                assert_eq!(preface::check_preface(&line[..length]), expected, "{:?}", &line[..length]);
+            }
+        }
+
+        let mut handshake = Handshake::new(Role::Server);
+        let request = b"POST /upload HTTP/1.1\r\n";
+        for length in 1..5 {
+            assert_eq!(handshake.receive_preface(&request[..length]), Ok(None));
+        }
+        assert_eq!(handshake.receive_preface(&request[..5]), Err(PrefaceError::Http11));
+    }
+
+    #[test]
+    fn test_handshake() {
+        let mut client = Peer::new(Role::Client);
+        let mut server = Peer::new(Role::Server);
+
+        let client_bytes = client.start(vec![Setting::EnablePush(0)]);
+        assert!(client_bytes.starts_with(PREFACE));
+        let server_bytes = server.start(vec![Setting::HeaderTableSize(256), Setting::MaxConcurrentStreams(100)]);
+        assert!(!server_bytes.starts_with(PREFACE));
+
+        // the server's preface arrives before the ACK of the client's SETTINGS
+        assert_eq!(server.handshake.receive_preface(&client_bytes[..5]), Ok(None));
+        let server_ack = server.receive(&client_bytes);
+        let client_ack = client.receive(&[server_bytes, server_ack].concat());
+        assert!(client.handshake.is_complete());
+        assert!(!server.handshake.is_complete());
+        server.receive(&client_ack);
+        assert!(server.handshake.is_complete());
+
+        assert!(!server.settings.remote().enable_push);
+        assert_eq!(client.settings.remote().max_concurrent_streams, Some(100));
+        assert_eq!(client.encoder.header_table_size(), 256);
+        assert_eq!(client.handshake.deadline(), None);
+    }
+
+    #[test]
+    fn test_http11_response() {
+        let mut handshake = Handshake::new(Role::Client);
+        assert_eq!(handshake.receive_preface(b"HTTP/1.1 400 Bad Request\r\n"), Err(PrefaceError::Http11));
+        assert_eq!(handshake.receive_preface(&[0, 0, 0, 4, 0, 0, 0, 0, 0]), Ok(Some(0)));
+    }
+
+    #[test]
+    fn test_expected_settings() {
+        let mut handshake = Handshake::new(Role::Client);
+        let ping = Frame::Ping(Ping {ack: false, payload: [0; 8]});
+        assert_eq!(handshake.receive_frame(&ping), Err(PrefaceError::ExpectedSettings(FrameKind::Ping)));
+
+        handshake.receive_frame(&Frame::Settings(Settings {ack: false, params: vec![]})).unwrap();
+        assert_eq!(handshake.receive_frame(&ping), Ok(()));
+    }
+
+    #[test]
+    fn test_settings_timeout() {
+        let mut client = Peer::new(Role::Client);
+        client.handshake.set_settings_timeout(Duration::from_secs(5));
+        assert_eq!(client.handshake.deadline(), None);
+
+        let mut bytes = vec![];
+        client.handshake.start(vec![], &mut client.settings, &mut client.decoder, Duration::from_secs(1), &mut bytes).unwrap();
+        assert_eq!(client.handshake.deadline(), Some(Duration::from_secs(6)));
+        assert_eq!(client.handshake.check_timeout(Duration::from_secs(5)), Ok(()));
+
+        let e = client.handshake.check_timeout(Duration::from_secs(6)).unwrap_err();
+        assert_eq!(e, PrefaceError::SettingsTimeout);
+        assert_eq!(e.code(), ErrorCode::SettingsTimeout);
+    }
+}
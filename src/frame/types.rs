@@ -0,0 +1,264 @@
+use alloc::vec::Vec;
+use crate::frame::error::ErrorCode;
+
+// frame header length, RFC 9113 section 4.1
+pub const HEADER_LEN: usize = 9;
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = 16_384;
+pub const MAX_MAX_FRAME_SIZE: u32 = 16_777_215;
+pub const MAX_STREAM_ID: u32 = 0x7fff_ffff;
+
+pub const END_STREAM: u8 = 0x1;
+pub const ACK: u8 = 0x1;
+pub const END_HEADERS: u8 = 0x4;
+pub const PADDED: u8 = 0x8;
+pub const PRIORITY: u8 = 0x20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    Data,
+    Headers,
+    Priority,
+    RstStream,
+    Settings,
+    PushPromise,
+    Ping,
+    GoAway,
+    WindowUpdate,
+    Continuation,
+    Unknown(u8),
+}
+
+impl From<u8> for FrameKind {
+    fn from(kind: u8) -> Self {
+        match kind {
+            0x0 => FrameKind::Data,
+            0x1 => FrameKind::Headers,
+            0x2 => FrameKind::Priority,
+            0x3 => FrameKind::RstStream,
+            0x4 => FrameKind::Settings,
+            0x5 => FrameKind::PushPromise,
+            0x6 => FrameKind::Ping,
+            0x7 => FrameKind::GoAway,
+            0x8 => FrameKind::WindowUpdate,
+            0x9 => FrameKind::Continuation,
+            x => FrameKind::Unknown(x),
+        }
+    }
+}
+
+impl From<FrameKind> for u8 {
+    fn from(kind: FrameKind) -> Self {
+        match kind {
+            FrameKind::Data => 0x0,
+            FrameKind::Headers => 0x1,
+            FrameKind::Priority => 0x2,
+            FrameKind::RstStream => 0x3,
+            FrameKind::Settings => 0x4,
+            FrameKind::PushPromise => 0x5,
+            FrameKind::Ping => 0x6,
+            FrameKind::GoAway => 0x7,
+            FrameKind::WindowUpdate => 0x8,
+            FrameKind::Continuation => 0x9,
+            FrameKind::Unknown(x) => x,
+        }
+    }
+}
+
+// Deprecated by RFC 9113 but still parsed, from HEADERS and PRIORITY frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamDependency {
+    pub stream_id: u32,
+    pub exclusive: bool,
+    // weight - 1, as sent on the wire
+    pub weight: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Data {
+    pub stream_id: u32,
+    pub data: Vec<u8>,
+    pub end_stream: bool,
+    // pad length, the padding itself is dropped when parsing
+    pub padding: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Headers {
+    pub stream_id: u32,
+    // header block fragment, continued by CONTINUATION frames without END_HEADERS
+    pub header_block: Vec<u8>,
+    pub end_stream: bool,
+    pub end_headers: bool,
+    pub dependency: Option<StreamDependency>,
+    pub padding: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Priority {
+    pub stream_id: u32,
+    pub dependency: StreamDependency,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RstStream {
+    pub stream_id: u32,
+    pub error_code: ErrorCode,
+}
+
+// RFC 9113 section 6.5.2, values are checked by the settings model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    HeaderTableSize(u32),
+    EnablePush(u32),
+    MaxConcurrentStreams(u32),
+    InitialWindowSize(u32),
+    MaxFrameSize(u32),
+    MaxHeaderListSize(u32),
+    // RFC 8441
+    EnableConnectProtocol(u32),
+    // unknown settings must be ignored
+    Unknown(u16, u32),
+}
+
+impl Setting {
+
+    pub fn new(id: u16, value: u32) -> Self {
+        match id {
+            0x1 => Setting::HeaderTableSize(value),
+            0x2 => Setting::EnablePush(value),
+            0x3 => Setting::MaxConcurrentStreams(value),
+            0x4 => Setting::InitialWindowSize(value),
+            0x5 => Setting::MaxFrameSize(value),
+            0x6 => Setting::MaxHeaderListSize(value),
+            0x8 => Setting::EnableConnectProtocol(value),
+            x => Setting::Unknown(x, value),
+        }
+    }
+
+    pub fn id(&self) -> u16 {
+        match self {
+            Setting::HeaderTableSize(_) => 0x1,
+            Setting::EnablePush(_) => 0x2,
+            Setting::MaxConcurrentStreams(_) => 0x3,
+            Setting::InitialWindowSize(_) => 0x4,
+            Setting::MaxFrameSize(_) => 0x5,
+            Setting::MaxHeaderListSize(_) => 0x6,
+            Setting::EnableConnectProtocol(_) => 0x8,
+            Setting::Unknown(x, _) => *x,
+        }
+    }
+
+    pub fn value(&self) -> u32 {
+        match self {
+            Setting::HeaderTableSize(x)
+            | Setting::EnablePush(x)
+            | Setting::MaxConcurrentStreams(x)
+            | Setting::InitialWindowSize(x)
+            | Setting::MaxFrameSize(x)
+            | Setting::MaxHeaderListSize(x)
+            | Setting::EnableConnectProtocol(x)
+            | Setting::Unknown(_, x) => *x,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub ack: bool,
+    // in the order received, later values for the same id win
+    pub params: Vec<Setting>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushPromise {
+    pub stream_id: u32,
+    pub promised_stream_id: u32,
+    pub header_block: Vec<u8>,
+    pub end_headers: bool,
+    pub padding: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ping {
+    pub ack: bool,
+    pub payload: [u8; 8],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoAway {
+    pub last_stream_id: u32,
+    pub error_code: ErrorCode,
+    pub debug_data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowUpdate {
+    // 0 for the connection window
+    pub stream_id: u32,
+    pub increment: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Continuation {
+    pub stream_id: u32,
+    pub header_block: Vec<u8>,
+    pub end_headers: bool,
+}
+
+// Frames of extension types, receivers must ignore them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unknown {
+    pub kind: u8,
+    pub flags: u8,
+    pub stream_id: u32,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Data(Data),
+    Headers(Headers),
+    Priority(Priority),
+    RstStream(RstStream),
+    Settings(Settings),
+    PushPromise(PushPromise),
+    Ping(Ping),
+    GoAway(GoAway),
+    WindowUpdate(WindowUpdate),
+    Continuation(Continuation),
+    Unknown(Unknown),
+}
+
+impl Frame {
+
+    pub fn kind(&self) -> FrameKind {
+        match self {
+            Frame::Data(_) => FrameKind::Data,
+            Frame::Headers(_) => FrameKind::Headers,
+            Frame::Priority(_) => FrameKind::Priority,
+            Frame::RstStream(_) => FrameKind::RstStream,
+            Frame::Settings(_) => FrameKind::Settings,
+            Frame::PushPromise(_) => FrameKind::PushPromise,
+            Frame::Ping(_) => FrameKind::Ping,
+            Frame::GoAway(_) => FrameKind::GoAway,
+            Frame::WindowUpdate(_) => FrameKind::WindowUpdate,
+            Frame::Continuation(_) => FrameKind::Continuation,
+            Frame::Unknown(x) => FrameKind::Unknown(x.kind),
+        }
+    }
+
+    // 0 for connection level frames
+    pub fn stream_id(&self) -> u32 {
+        match self {
+            Frame::Data(x) => x.stream_id,
+            Frame::Headers(x) => x.stream_id,
+            Frame::Priority(x) => x.stream_id,
+            Frame::RstStream(x) => x.stream_id,
+            Frame::PushPromise(x) => x.stream_id,
+            Frame::WindowUpdate(x) => x.stream_id,
+            Frame::Continuation(x) => x.stream_id,
+            Frame::Unknown(x) => x.stream_id,
+            Frame::Settings(_) | Frame::Ping(_) | Frame::GoAway(_) => 0,
+        }
+    }
+}
@@ -1,7 +1,16 @@
 pub mod encoder;
 pub mod decoder;
+pub mod disassembler;
+pub mod head;
 pub mod header;
 pub mod header_table;
+pub mod hex;
+pub mod huffman;
+#[cfg(feature = "http")]
+pub mod interop;
+pub mod stats;
+pub mod story;
+pub mod validation;
 
 #[cfg(test)]
 mod tests;
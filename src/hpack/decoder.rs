@@ -1,81 +1,341 @@
-use crate::hpack::header::Header;
-use crate::hpack::header_table::HeaderTable;
+use alloc::boxed::Box;
+use alloc::borrow::{Cow, ToOwned};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::hpack::header::{EncodableHeader, Header, HeaderName};
+use crate::hpack::header_table::{HeaderTable, SnapshotError, TableObserver, TableSnapshot};
+use crate::hpack::huffman;
+use crate::hpack::stats::CompressionStats;
+use crate::hpack::validation::{self, Validation, ValidationError};
 
-pub(crate) struct Decoder{
-    pub header_table: HeaderTable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecoderError {
+    Malformed(ValidationError),
+    EmptyData,
+    Truncated,
+    IntegerOverflow,
+    UnsupportedRepresentation,
+    InvalidIndex,
+    InvalidHuffman,
+    InvalidUtf8,
+    InvalidTableSizeUpdate,
+    // the block does not start with the update a lowered maximum requires
+    MissingTableSizeUpdate,
+    // an update after a field, or more than two in a block
+    UnexpectedTableSizeUpdate,
+    HeaderListTooLarge,
+    StringTooLong,
+    TooManyFields,
+    ExpansionRatioExceeded,
+}
+
+impl fmt::Display for DecoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            DecoderError::Malformed(e) => return write!(f, "Malformed header list: {}", e),
+            DecoderError::EmptyData => "Decode data is empty!",
+            DecoderError::Truncated => "Decode data is truncated!",
+            DecoderError::IntegerOverflow => "Integer overflow!",
+            DecoderError::UnsupportedRepresentation => "Unsupported decode!",
+            DecoderError::InvalidIndex => "Invalid header table index!",
+            DecoderError::InvalidHuffman => "Invalid Huffman code!",
+            DecoderError::InvalidUtf8 => "Invalid UTF-8!",
+            DecoderError::InvalidTableSizeUpdate => "Table size update exceeds maximum!",
+            DecoderError::MissingTableSizeUpdate => "Expected a table size update at the start of the block!",
+            DecoderError::UnexpectedTableSizeUpdate => "Table size update not at the start of the block!",
+            DecoderError::HeaderListTooLarge => "Header list size exceeded!",
+            DecoderError::StringTooLong => "String length exceeded!",
+            DecoderError::TooManyFields => "Header field count exceeded!",
+            DecoderError::ExpansionRatioExceeded => "Expansion ratio exceeded!",
+        };
+        f.write_str(message)
+    }
+}
+
+impl core::error::Error for DecoderError {}
+
+// Limits against compression bombs. Unlike the header list size these abort
+// decoding immediately, so the connection should be torn down on error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecoderPolicy {
+    pub max_string_length: usize,
+    pub max_field_count: usize,
+    // decoded name and value octets allowed per encoded octet of a block
+    pub max_expansion_ratio: usize,
+    // decoded octets below which the expansion ratio is not enforced
+    pub expansion_ratio_threshold: usize,
+}
+
+// The defaults follow the common 64 KiB header list limit: no string can be
+// longer, and each field costs at least 32 octets of it, so no more than 2048
+// fields fit. Past 64 KiB of output a block may expand at most 64 times, which
+// literals never do but repeated references to large table entries will.
+impl Default for DecoderPolicy {
+    fn default() -> Self {
+        DecoderPolicy {
+            max_string_length: 65_536,
+            max_field_count: 2_048,
+            max_expansion_ratio: 64,
+            expansion_ratio_threshold: 65_536,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepresentationKind {
+    Indexed,
+    IncrementalIndexing,
+    WithoutIndexing,
+    NeverIndexed,
+    SizeUpdate,
+}
+
+impl fmt::Display for RepresentationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RepresentationKind::Indexed => "indexed field",
+            RepresentationKind::IncrementalIndexing => "literal with incremental indexing",
+            RepresentationKind::WithoutIndexing => "literal without indexing",
+            RepresentationKind::NeverIndexed => "literal never indexed",
+            RepresentationKind::SizeUpdate => "dynamic table size update",
+        };
+        f.write_str(name)
+    }
+}
+
+// One decoded field representation of a header block (RFC 7541 section 6).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Representation {
+    pub kind: RepresentationKind,
+    // index of the field or its name, 0 for a literal name, the new size for size updates
+    pub index: u64,
+    pub header: Option<Header>,
+    pub name_huffman: bool,
+    pub value_huffman: bool,
+    // octets consumed
+    pub length: usize,
+}
+
+// Decoder state for continuing a connection elsewhere, the table plus whether
+// the next block still has to confirm a lowered maximum.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecoderSnapshot {
+    pub table: TableSnapshot,
+    pub size_update_required: bool,
+}
+
+pub struct DecoderBuilder {
+    header_table_size: Option<usize>,
+    max_header_table_size: usize,
+    max_header_list_size: usize,
+    policy: DecoderPolicy,
+    validation: Validation,
+}
+
+impl DecoderBuilder {
+
+    pub fn new() -> Self {
+        Self {
+            header_table_size: None,
+            max_header_table_size: 4096,
+            max_header_list_size: usize::MAX,
+            policy: DecoderPolicy::default(),
+            validation: Validation::Disabled,
+        }
+    }
+
+    // table size before the peer's first update, defaults to the maximum
+    pub fn header_table_size(mut self, size: usize) -> Self {
+        self.header_table_size = Some(size);
+        self
+    }
+
+    // our SETTINGS_HEADER_TABLE_SIZE, larger table size updates are rejected
+    pub fn max_header_table_size(mut self, size: usize) -> Self {
+        self.max_header_table_size = size;
+        self
+    }
+
+    // our SETTINGS_MAX_HEADER_LIST_SIZE
+    pub fn max_header_list_size(mut self, size: usize) -> Self {
+        self.max_header_list_size = size;
+        self
+    }
+
+    pub fn policy(mut self, policy: DecoderPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn validation(mut self, validation: Validation) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    pub fn build(self) -> Decoder {
+        let table_size = self.header_table_size
+            .unwrap_or(self.max_header_table_size)
+            .min(self.max_header_table_size);
+
+        Decoder {
+            header_table: HeaderTable::new(table_size),
+            max_header_table_size: self.max_header_table_size,
+            size_update_required: false,
+            max_header_list_size: self.max_header_list_size,
+            policy: self.policy,
+            validation: self.validation,
+            stats: CompressionStats::default(),
+            eviction_base: 0,
+        }
+    }
+}
+
+impl Default for DecoderBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Decoder{
+    header_table: HeaderTable,
+    max_header_table_size: usize,
+    // set when the maximum drops below the table size
+    size_update_required: bool,
+    max_header_list_size: usize,
+    policy: DecoderPolicy,
+    validation: Validation,
+    stats: CompressionStats,
+    eviction_base: u64,
 }
 
 impl Decoder {
 
-    pub(crate) fn new() -> Decoder {
-        Decoder {header_table: HeaderTable::new_default()}
+    pub fn new() -> Decoder {
+        DecoderBuilder::new().build()
+    }
+
+    pub fn builder() -> DecoderBuilder {
+        DecoderBuilder::new()
+    }
+
+    pub fn decode(&mut self, data: &[u8]) -> Result<Vec<Header>, DecoderError> {
+        let headers = self.decode_headers(data)?;
+        Ok(headers.into_iter().map(|x| Header {name: x.name, value: x.value}).collect())
     }
 
-    pub(crate) fn decode(&mut self, data: &[u8]) -> Result<Vec<Header>, String> {
-        let mut headers: Vec<Header> = vec![];
+    // like decode, but marks fields the peer sent as never indexed as sensitive
+    pub fn decode_headers(&mut self, data: &[u8]) -> Result<Vec<EncodableHeader>, DecoderError> {
+        let mut headers: Vec<EncodableHeader> = vec![];
+        let mut header_list_size: usize = 0;
+        let mut field_count: usize = 0;
+        let mut decoded_octets: usize = 0;
+        let mut size_updates: usize = 0;
         let mut index: usize = 0;
+        self.stats.blocks += 1;
+        self.stats.encoded_bytes += data.len() as u64;
 
         while index != data.len() {
-            let byte = data[index];
-
-            if byte & 0b1000_0000 == 0b1000_0000 {
-                // Indexed Header Field Representation
-                let (header, consumed) = match self.decode_indexed(&data[index..data.len()]) {
-                    Ok(x) => x,
-                    Err(e) => return Err(e),
-                };
-                headers.push(header);
-                index += consumed
-            } else if byte & 0b1100_0000 == 0b0100_0000 {
-                // Literal Header Field with Incremental Indexing
-                let (header, consumed) = match self.decode_literal(&data[index..data.len()], 6) {
-                    Ok(x) => x,
-                    Err(e) => return Err(e),
-                };
-                self.header_table.add(header.name.as_str(), header.value.as_str());
-                headers.push(header);
-                index += consumed;
-            } else if byte & 0b1111_0000 == 0b0000_0000 {
-                // Literal Header Field without Indexing
-                let (header, consumed) = match self.decode_literal(&data[index..data.len()], 4) {
-                    Ok(x) => x,
-                    Err(e) => return Err(e),
-                };
-                headers.push(header);
-                index += consumed;
-            } else if byte & 0b1111_0000 == 0b0001_0000 {
-                // Literal Header Field never Indexed
-                index += 1;
-                let (name, name_consumed) = match self.decode_string(&data[index..data.len()]) {
-                    Ok(x) => x,
-                    Err(e) => return Err(e),
-                };
-                index += name_consumed;
-                let (value, value_consumed) = match self.decode_string(&data[index..data.len()]) {
-                    Ok(x) => x,
-                    Err(e) => return Err(e),
-                };
-                headers.push(Header{name, value});
-                index += value_consumed;
-            } else if byte & 0b1110_0000 == 0b0010_0000 {
-                // Dynamic Table Size Update
-                let (new_size, consumed) = match self.decode_int(&data[index..data.len()], 5) {
-                    Ok(x) => x,
-                    Err(e) => return Err(e)
-                };
-                index += consumed;
-                self.header_table.set_max_size(new_size as usize);
-            } else {
-                return Err("Unsupported decode!".to_string());
+            let representation = self.decode_representation(&data[index..data.len()])?;
+            index += representation.length;
+
+            // RFC 7541 section 4.2: at most two updates, before the first field
+            if representation.kind == RepresentationKind::SizeUpdate {
+                size_updates += 1;
+                if field_count > 0 || size_updates > 2 {
+                    return Err(DecoderError::UnexpectedTableSizeUpdate);
+                }
+                self.size_update_required = false;
+                continue;
+            }
+            if self.size_update_required {
+                return Err(DecoderError::MissingTableSizeUpdate);
+            }
+
+            let Some(header) = representation.header else { continue };
+            let is_sensitive = representation.kind == RepresentationKind::NeverIndexed;
+            self.stats.headers += 1;
+            self.stats.raw_bytes += (header.name.len() + header.value.len()) as u64;
+
+            field_count += 1;
+            if field_count > self.policy.max_field_count {
+                return Err(DecoderError::TooManyFields);
+            }
+
+            decoded_octets = decoded_octets.saturating_add(header.name.len() + header.value.len());
+            if decoded_octets > self.policy.expansion_ratio_threshold
+                && decoded_octets > self.policy.max_expansion_ratio.saturating_mul(data.len()) {
+                return Err(DecoderError::ExpansionRatioExceeded);
+            }
+
+            // Once the limit is hit the remaining fields are still decoded so the
+            // dynamic table stays in sync with the peer, but nothing more is kept.
+            header_list_size = header_list_size.saturating_add(header.size());
+            if header_list_size <= self.max_header_list_size {
+                headers.push(EncodableHeader {name: header.name, value: header.value, is_sensitive});
             }
         }
 
+        if self.size_update_required {
+            return Err(DecoderError::MissingTableSizeUpdate);
+        }
+        if header_list_size > self.max_header_list_size {
+            return Err(DecoderError::HeaderListTooLarge);
+        }
+
+        validation::validate_encodable(&headers, self.validation).map_err(DecoderError::Malformed)?;
         Ok(headers)
     }
 
-    pub(crate) fn decode_int(&self, data: &[u8], prefix_bits: u32) -> Result<(u64, usize), String> {
+    // Decodes the representation at the start of data and applies it to the
+    // dynamic table, without any of the header list limits.
+    pub fn decode_representation(&mut self, data: &[u8]) -> Result<Representation, DecoderError> {
+        let byte = *data.first().ok_or(DecoderError::EmptyData)?;
+
+        if byte & 0b1000_0000 == 0b1000_0000 {
+            let representation = self.decode_indexed(data)?;
+            self.stats.indexed += 1;
+            Ok(representation)
+        } else if byte & 0b1100_0000 == 0b0100_0000 {
+            let representation = self.decode_literal(data, RepresentationKind::IncrementalIndexing)?;
+            if let Some(header) = &representation.header {
+                self.header_table.add(header.name.as_str(), header.value.as_str());
+            }
+            self.stats.literal_incremental += 1;
+            Ok(representation)
+        } else if byte & 0b1111_0000 == 0b0000_0000 {
+            let representation = self.decode_literal(data, RepresentationKind::WithoutIndexing)?;
+            self.stats.literal_without_indexing += 1;
+            Ok(representation)
+        } else if byte & 0b1111_0000 == 0b0001_0000 {
+            let representation = self.decode_literal(data, RepresentationKind::NeverIndexed)?;
+            self.stats.literal_never_indexed += 1;
+            Ok(representation)
+        } else if byte & 0b1110_0000 == 0b0010_0000 {
+            let (new_size, consumed) = self.decode_int(data, 5)?;
+            if new_size > self.max_header_table_size as u64 {
+                return Err(DecoderError::InvalidTableSizeUpdate);
+            }
+            self.header_table.set_max_size(new_size as usize);
+            self.stats.table_size_updates += 1;
+            Ok(Representation {
+                kind: RepresentationKind::SizeUpdate,
+                index: new_size,
+                header: None,
+                name_huffman: false,
+                value_huffman: false,
+                length: consumed,
+            })
+        } else {
+            Err(DecoderError::UnsupportedRepresentation)
+        }
+    }
+
+    pub fn decode_int(&self, data: &[u8], prefix_bits: u32) -> Result<(u64, usize), DecoderError> {
         if data.is_empty() {
-            return Err("Decode data is empty!".to_string());
+            return Err(DecoderError::EmptyData);
         }
 
         let max_number = 2_u64.pow(prefix_bits) - 1;
@@ -89,8 +349,11 @@ impl Decoder {
         let mut m = 0;
         loop {
             consumed += 1;
-            let next = data[consumed - 1];
-            number += (next as u64 & 127) * 2_u64.pow(m);
+            let next = *data.get(consumed - 1).ok_or(DecoderError::Truncated)?;
+            let addition = (next as u64 & 127).checked_shl(m)
+                .filter(|x| x >> m == next as u64 & 127)
+                .ok_or(DecoderError::IntegerOverflow)?;
+            number = number.checked_add(addition).ok_or(DecoderError::IntegerOverflow)?;
             m += 7;
 
             if next & 128 != 128 {
@@ -101,40 +364,187 @@ impl Decoder {
         Ok((number, consumed))
     }
 
-    pub(crate) fn decode_literal(&self, data: &[u8], prefix: u32) -> Result<(Header, usize), String> {
+    pub fn decode_literal(&mut self, data: &[u8], kind: RepresentationKind) -> Result<Representation, DecoderError> {
+        let prefix = if kind == RepresentationKind::IncrementalIndexing { 6 } else { 4 };
         let (index, mut consumed) = self.decode_int(data, prefix)?;
 
-        let name: String;
+        let name: HeaderName;
+        let mut name_huffman = false;
         if index == 0 {
             let name_data = &data[consumed..data.len()];
             let name_result = self.decode_string(name_data)?;
-            name = name_result.0;
+            name = name_result.0.into();
+            name_huffman = name_data[0] & 128 > 0;
             consumed += name_result.1
         } else {
-            let header = self.header_table[index.try_into().unwrap()].clone();
-            name = header.name;
+            name = self.table_name(index)?;
+            self.stats.record_index(index as usize);
         }
 
         let value_data = &data[consumed..data.len()];
         let value_result = self.decode_string(value_data)?;
-        Ok((Header {name, value: value_result.0}, consumed + value_result.1))
+        Ok(Representation {
+            kind,
+            index,
+            header: Some(Header {name, value: value_result.0}),
+            name_huffman,
+            value_huffman: value_data[0] & 128 > 0,
+            length: consumed + value_result.1,
+        })
     }
 
-    fn decode_string(&self, data: &[u8]) -> Result<(String, usize), String> {
+    fn decode_string(&mut self, data: &[u8]) -> Result<(String, usize), DecoderError> {
         let (length, consumed) = self.decode_int(data, 7)?;
+        if length > self.policy.max_string_length as u64 {
+            return Err(DecoderError::StringTooLong);
+        }
+        if length > (data.len() - consumed) as u64 {
+            return Err(DecoderError::Truncated);
+        }
+
         let end_idx = consumed + length as usize;
-        let bytes = data[consumed..end_idx].to_owned();
+        let mut bytes = data[consumed..end_idx].to_owned();
 
-        if !bytes.is_empty() && bytes[0] & 128 > 0 {
-            return Err("Huffman not supported".to_string());
+        if data[0] & 128 > 0 {
+            bytes = huffman::decode(&bytes).ok_or(DecoderError::InvalidHuffman)?;
+            self.stats.record_huffman(bytes.len(), length as usize);
+            if bytes.len() > self.policy.max_string_length {
+                return Err(DecoderError::StringTooLong);
+            }
         }
 
-        let value = String::from_utf8(bytes);
-        Ok((value.unwrap(), end_idx))
+        let value = String::from_utf8(bytes).map_err(|_| DecoderError::InvalidUtf8)?;
+        Ok((value, end_idx))
     }
 
-    pub(crate) fn decode_indexed(&self, data: &[u8]) -> Result<(Header, usize), String> {
+    pub fn decode_indexed(&mut self, data: &[u8]) -> Result<Representation, DecoderError> {
         let (index, consumed) = self.decode_int(data, 7)?;
-        Ok((self.header_table[index.try_into().unwrap()].clone(), consumed))
+        let header = self.table_entry(index)?.into_owned();
+        self.stats.record_index(index as usize);
+        Ok(Representation {
+            kind: RepresentationKind::Indexed,
+            index,
+            header: Some(header),
+            name_huffman: false,
+            value_huffman: false,
+            length: consumed,
+        })
+    }
+
+    fn table_entry(&self, index: u64) -> Result<Cow<'_, Header>, DecoderError> {
+        usize::try_from(index).ok()
+            .and_then(|x| self.header_table.get(x))
+            .ok_or(DecoderError::InvalidIndex)
+    }
+
+    fn table_name(&self, index: u64) -> Result<HeaderName, DecoderError> {
+        usize::try_from(index).ok()
+            .and_then(|x| self.header_table.get_name(x))
+            .ok_or(DecoderError::InvalidIndex)
+    }
+
+    pub fn snapshot(&self) -> DecoderSnapshot {
+        DecoderSnapshot {
+            table: self.header_table.snapshot(),
+            size_update_required: self.size_update_required,
+        }
+    }
+
+    // Sizes are checked against max_header_table_size, the observer is kept.
+    pub fn restore(&mut self, snapshot: &DecoderSnapshot) -> Result<(), SnapshotError> {
+        if snapshot.table.max_size > self.max_header_table_size {
+            return Err(SnapshotError::ExceedsLimit);
+        }
+
+        let mut table = HeaderTable::restore(&snapshot.table)?;
+        if let Some(observer) = self.header_table.take_observer() {
+            table.set_observer(observer);
+        }
+        self.header_table = table;
+        self.size_update_required = snapshot.size_update_required;
+        self.eviction_base = 0;
+        Ok(())
+    }
+
+    pub fn header_table(&self) -> &HeaderTable {
+        &self.header_table
+    }
+
+    pub fn set_observer(&mut self, observer: Box<dyn TableObserver + Send>) {
+        self.header_table.set_observer(observer);
+    }
+
+    pub fn take_observer(&mut self) -> Option<Box<dyn TableObserver + Send>> {
+        self.header_table.take_observer()
+    }
+
+    pub fn header_table_size(&self) -> usize {
+        self.header_table.max_size()
+    }
+
+    pub fn max_header_table_size(&self) -> usize {
+        self.max_header_table_size
+    }
+
+    // A lower maximum, e.g. our acknowledged SETTINGS_HEADER_TABLE_SIZE, shrinks
+    // the table right away and the peer's next block must confirm it.
+    pub fn set_max_header_table_size(&mut self, size: usize) {
+        self.max_header_table_size = size;
+        if size < self.header_table.max_size() {
+            self.header_table.set_max_size(size);
+            self.size_update_required = true;
+        }
+    }
+
+    pub fn max_header_list_size(&self) -> usize {
+        self.max_header_list_size
+    }
+
+    pub fn set_max_header_list_size(&mut self, size: usize) {
+        self.max_header_list_size = size;
+    }
+
+    pub fn policy(&self) -> DecoderPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: DecoderPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn validation(&self) -> Validation {
+        self.validation
+    }
+
+    pub fn set_validation(&mut self, validation: Validation) {
+        self.validation = validation;
+    }
+
+    // Runs f without counting anything it decodes, e.g. for the disassembler.
+    pub(crate) fn without_stats<T>(&mut self, f: impl FnOnce(&mut Decoder) -> T) -> T {
+        let stats = self.stats;
+        let evictions = self.header_table.eviction_count();
+        let result = f(self);
+        self.stats = stats;
+        self.eviction_base += self.header_table.eviction_count() - evictions;
+        result
+    }
+
+    pub fn stats(&self) -> CompressionStats {
+        CompressionStats {
+            evictions: self.header_table.eviction_count().saturating_sub(self.eviction_base),
+            ..self.stats
+        }
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = CompressionStats::default();
+        self.eviction_base = self.header_table.eviction_count();
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
     }
 }
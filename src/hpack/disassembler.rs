@@ -0,0 +1,109 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::hpack::decoder::{Decoder, DecoderError, Representation, RepresentationKind};
+use crate::hpack::header_table::TableSnapshot;
+use crate::hpack::hex;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    // offset of the representation in the header block
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+    pub representation: Representation,
+    // dynamic table right after the representation was applied
+    pub table: TableSnapshot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    pub steps: Vec<Step>,
+    // offset of the representation that failed to decode
+    pub error: Option<(usize, DecoderError)>,
+    // undecoded bytes, starting at the error offset
+    pub remaining: Vec<u8>,
+}
+
+impl Disassembly {
+
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{:04x}  {}", step.offset, hex::encode_spaced(&step.bytes))?;
+
+            let representation = &step.representation;
+            match representation.kind {
+                RepresentationKind::SizeUpdate => {
+                    writeln!(f, "      {} to {}", representation.kind, representation.index)?;
+                }
+                RepresentationKind::Indexed => {
+                    writeln!(f, "      {} {}", representation.kind, representation.index)?;
+                }
+                _ if representation.index == 0 => {
+                    writeln!(
+                        f, "      {}, literal name (huffman: {}), value (huffman: {})",
+                        representation.kind, representation.name_huffman, representation.value_huffman,
+                    )?;
+                }
+                _ => {
+                    writeln!(
+                        f, "      {}, indexed name {}, value (huffman: {})",
+                        representation.kind, representation.index, representation.value_huffman,
+                    )?;
+                }
+            }
+
+            if let Some(header) = &representation.header {
+                writeln!(f, "      {}: {}", header.name, header.value)?;
+            }
+            writeln!(
+                f, "      table: {} entries, size {}/{}",
+                step.table.entries.len(), step.table.size, step.table.max_size,
+            )?;
+        }
+
+        if let Some((offset, e)) = &self.error {
+            writeln!(f, "{:04x}  {}", offset, hex::encode_spaced(&self.remaining))?;
+            writeln!(f, "      error: {}", e)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Walks a header block one representation at a time, stopping at the first
+// one that fails to decode. The decoder's dynamic table is updated as usual,
+// its compression stats are not.
+pub fn disassemble(decoder: &mut Decoder, data: &[u8]) -> Disassembly {
+    decoder.without_stats(|decoder| disassemble_block(decoder, data))
+}
+
+fn disassemble_block(decoder: &mut Decoder, data: &[u8]) -> Disassembly {
+    let mut steps: Vec<Step> = vec![];
+    let mut offset: usize = 0;
+
+    while offset != data.len() {
+        match decoder.decode_representation(&data[offset..data.len()]) {
+            Ok(representation) => {
+                let end = offset + representation.length;
+                steps.push(Step {
+                    offset,
+                    bytes: data[offset..end].to_vec(),
+                    representation,
+                    table: decoder.header_table().snapshot(),
+                });
+                offset = end;
+            }
+            Err(e) => {
+                return Disassembly {steps, error: Some((offset, e)), remaining: data[offset..].to_vec()};
+            }
+        }
+    }
+
+    Disassembly {steps, error: None, remaining: vec![]}
+}
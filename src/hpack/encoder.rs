@@ -1,79 +1,226 @@
-use crate::hpack::header::{EncodableHeader, Header};
-use crate::hpack::header_table::HeaderTable;
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::hpack::header::{EncodableHeader, Header, HeaderName};
+use crate::hpack::header_table::{HeaderTable, SnapshotError, TableObserver, TableSnapshot};
+use crate::hpack::huffman;
+use crate::hpack::stats::CompressionStats;
+use crate::hpack::validation::{self, Validation, ValidationError};
 
-pub(crate) struct Encoder{
-    pub header_table: HeaderTable,
-    header_table_changes:Vec<usize>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HuffmanPolicy {
+    Never,
+    Always,
+    // Huffman code a string only when that makes it shorter
+    Shortest,
 }
 
-impl Encoder {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexingStrategy {
+    // add every non-sensitive literal to the dynamic table
+    Incremental,
+    // never touch the dynamic table, only static and existing entries are indexed
+    WithoutIndexing,
+}
 
-    const INDEX_NEVER: u8 = 16;
-    const INDEX_INCREMENTAL: u8 = 68;
+// Encoder state for continuing a connection elsewhere, the table plus the
+// size updates it still has to signal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EncoderSnapshot {
+    pub table: TableSnapshot,
+    pub pending_table_sizes: Vec<usize>,
+}
+
+pub struct EncoderBuilder {
+    header_table_size: usize,
+    max_header_table_size: usize,
+    huffman: HuffmanPolicy,
+    indexing: IndexingStrategy,
+    validation: Validation,
+}
+
+impl EncoderBuilder {
 
-    pub(crate) fn new() -> Self {
+    pub fn new() -> Self {
         Self {
-            header_table: HeaderTable::new_default(),
-            header_table_changes: vec![],
+            header_table_size: 4096,
+            max_header_table_size: 4096,
+            huffman: HuffmanPolicy::Never,
+            indexing: IndexingStrategy::Incremental,
+            validation: Validation::Disabled,
         }
     }
 
-    pub(crate) fn encode(&mut self, headers: &[Header]) -> Vec<u8> {
-        let tuples: Vec<EncodableHeader> = headers.iter()
-            .map(|x| {
-                EncodableHeader {
-                    name: x.name.clone(),
-                    value: x.value.clone(),
-                    is_sensitive: false,
-                }
-            })
-            .collect();
+    // table size the encoder uses, signalled to the peer if below the maximum
+    pub fn header_table_size(mut self, size: usize) -> Self {
+        self.header_table_size = size;
+        self
+    }
+
+    // the peer's SETTINGS_HEADER_TABLE_SIZE
+    pub fn max_header_table_size(mut self, size: usize) -> Self {
+        self.max_header_table_size = size;
+        self
+    }
+
+    pub fn huffman(mut self, policy: HuffmanPolicy) -> Self {
+        self.huffman = policy;
+        self
+    }
 
-        self.encode_headers(tuples.as_slice())
+    pub fn indexing(mut self, strategy: IndexingStrategy) -> Self {
+        self.indexing = strategy;
+        self
     }
 
-    pub(crate) fn encode_headers(&mut self, headers: &[EncodableHeader]) -> Vec<u8> {
+    // applied by try_encode and try_encode_headers
+    pub fn validation(mut self, validation: Validation) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    pub fn build(self) -> Encoder {
+        let mut encoder = Encoder {
+            header_table: HeaderTable::new(self.max_header_table_size),
+            header_table_changes: vec![],
+            max_header_table_size: self.max_header_table_size,
+            huffman: self.huffman,
+            indexing: self.indexing,
+            validation: self.validation,
+            stats: CompressionStats::default(),
+            eviction_base: 0,
+        };
+        encoder.set_header_table_size(self.header_table_size);
+
+        encoder
+    }
+}
+
+impl Default for EncoderBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Encoder{
+    header_table: HeaderTable,
+    header_table_changes:Vec<usize>,
+    max_header_table_size: usize,
+    huffman: HuffmanPolicy,
+    indexing: IndexingStrategy,
+    validation: Validation,
+    stats: CompressionStats,
+    eviction_base: u64,
+}
+
+impl Encoder {
+
+    const INDEX_NONE: u8 = 0;
+    const INDEX_NEVER: u8 = 16;
+    const INDEX_INCREMENTAL: u8 = 64;
+
+    pub fn new() -> Self {
+        EncoderBuilder::new().build()
+    }
+
+    pub fn builder() -> EncoderBuilder {
+        EncoderBuilder::new()
+    }
+
+    pub fn encode(&mut self, headers: &[Header]) -> Vec<u8> {
+        let mut encoded = self.encode_header_table_changes();
+        for header in headers {
+            encoded.append(&mut self.encode_header(&header.name, &header.value, false));
+        }
+
+        self.stats.blocks += 1;
+        self.stats.encoded_bytes += encoded.len() as u64;
+        encoded
+    }
+
+    pub fn encode_headers(&mut self, headers: &[EncodableHeader]) -> Vec<u8> {
         let mut encoded = self.encode_header_table_changes();
-        let mut encoded_headers: Vec<u8> = headers.iter()
-            .map(|x| self.encode_header(&x.name, &x.value, x.is_sensitive))
-            .flatten()
-            .collect();
-        encoded.append(&mut encoded_headers);
+        for header in headers {
+            encoded.append(&mut self.encode_header(&header.name, &header.value, header.is_sensitive));
+        }
 
+        self.stats.blocks += 1;
+        self.stats.encoded_bytes += encoded.len() as u64;
         encoded
     }
 
-    fn encode_header_table_changes(&self) -> Vec<u8> {
-        return self.header_table_changes.iter().map(|size| {
-            let mut bytes = self.encode_int(size.clone() as u64, 5);
+    pub fn try_encode(&mut self, headers: &[Header]) -> Result<Vec<u8>, ValidationError> {
+        validation::validate(headers, self.validation)?;
+        Ok(self.encode(headers))
+    }
+
+    pub fn try_encode_headers(&mut self, headers: &[EncodableHeader]) -> Result<Vec<u8>, ValidationError> {
+        validation::validate_encodable(headers, self.validation)?;
+        Ok(self.encode_headers(headers))
+    }
+
+    // RFC 7541 section 4.2: the smallest size since the last block evicts the
+    // same entries as every change in between, so at most two are signalled
+    fn encode_header_table_changes(&mut self) -> Vec<u8> {
+        let changes = core::mem::take(&mut self.header_table_changes);
+        let changes = match (changes.iter().min(), changes.last()) {
+            (Some(min), Some(last)) if min < last => vec![*min, *last],
+            (_, Some(last)) => vec![*last],
+            _ => vec![],
+        };
+        self.stats.table_size_updates += changes.len() as u64;
+        changes.iter().flat_map(|size| {
+            let mut bytes = self.encode_int(*size as u64, 5);
             bytes[0] |= 0x20;
             bytes
-        }).flatten().collect()
+        }).collect()
     }
 
-    fn encode_header(&mut self, name: &str, value: &str, sensitive: bool) -> Vec<u8> {
-        if let Some(x) = self.header_table.search_with_name_and_value(name, value) {
+    fn encode_header(&mut self, name: &HeaderName, value: &str, sensitive: bool) -> Vec<u8> {
+        self.stats.headers += 1;
+        self.stats.raw_bytes += (name.len() + value.len()) as u64;
+
+        // an indexed field would drop the never indexed flag for intermediaries
+        let indexed = if sensitive { None } else { self.header_table.search_with_header_name_and_value(name, value) };
+        if let Some(x) = indexed {
+            self.stats.indexed += 1;
+            self.stats.record_index(x);
             return self.encode_indexed(x);
         }
 
-        if let Some(x) = self.header_table.search_with_name(name) {
-            let index_bit = if sensitive {
-                Encoder::INDEX_NEVER
-            } else {
-                Encoder::INDEX_INCREMENTAL
-            };
+        let index_bit = if sensitive {
+            Encoder::INDEX_NEVER
+        } else if self.indexing == IndexingStrategy::Incremental {
+            Encoder::INDEX_INCREMENTAL
+        } else {
+            Encoder::INDEX_NONE
+        };
+
+        match index_bit {
+            Encoder::INDEX_NEVER => self.stats.literal_never_indexed += 1,
+            Encoder::INDEX_INCREMENTAL => self.stats.literal_incremental += 1,
+            _ => self.stats.literal_without_indexing += 1,
+        }
 
-            if !sensitive {
-                self.header_table.add(name, value);
+        if let Some(x) = self.header_table.search_with_header_name(name) {
+            self.stats.record_index(x);
+            if index_bit == Encoder::INDEX_INCREMENTAL {
+                self.header_table.add_header(Header {name: name.clone(), value: value.to_string()});
             }
 
             return self.encode_indexed_literal(x as u64, value, index_bit);
         }
 
-        self.encode_literal(name, value)
+        if index_bit == Encoder::INDEX_INCREMENTAL {
+            self.header_table.add_header(Header {name: name.clone(), value: value.to_string()});
+        }
+
+        self.encode_literal(name, value, index_bit)
     }
-    
-    pub(crate) fn encode_int(&self, value: u64, prefix_bits: u32) -> Vec<u8> {
+
+    pub fn encode_int(&self, value: u64, prefix_bits: u32) -> Vec<u8> {
         let max_number = 2_u64.pow(prefix_bits) - 1;
         if value < max_number {
             return vec![value as u8];
@@ -91,23 +238,42 @@ impl Encoder {
         encoded
     }
 
-    pub(crate) fn encode_literal(&self, name: &str, value: &str) -> Vec<u8> {
-        let mut encoded = vec![0u8];
-        encoded.append(&mut self.encode_int(name.len() as u64, 7));
-        encoded.append(&mut name.as_bytes().to_vec());
-        encoded.append(&mut self.encode_int(value.len() as u64, 7));
-        encoded.append(&mut value.as_bytes().to_vec());
+    pub fn encode_string(&mut self, value: &str) -> Vec<u8> {
+        let use_huffman = match self.huffman {
+            HuffmanPolicy::Never => false,
+            HuffmanPolicy::Always => true,
+            HuffmanPolicy::Shortest => huffman::encoded_len(value.as_bytes()) < value.len(),
+        };
+
+        if !use_huffman {
+            let mut encoded = self.encode_int(value.len() as u64, 7);
+            encoded.append(&mut value.as_bytes().to_vec());
+            return encoded;
+        }
+
+        let mut data = huffman::encode(value.as_bytes());
+        self.stats.record_huffman(value.len(), data.len());
+        let mut encoded = self.encode_int(data.len() as u64, 7);
+        encoded[0] |= 0x80;
+        encoded.append(&mut data);
+        encoded
+    }
+
+    pub fn encode_literal(&mut self, name: &str, value: &str, index_bit: u8) -> Vec<u8> {
+        let mut encoded = vec![index_bit];
+        encoded.append(&mut self.encode_string(name));
+        encoded.append(&mut self.encode_string(value));
 
         encoded
     }
 
-    pub(crate) fn encode_indexed(&self, index: usize) -> Vec<u8> {
+    pub fn encode_indexed(&self, index: usize) -> Vec<u8> {
         let mut bytes = self.encode_int(index as u64, 7);
         bytes[0] |= 0x80;
-        return bytes;
+        bytes
     }
 
-    pub(crate) fn encode_indexed_literal(&self, index: u64, value: &str, index_bit: u8) -> Vec<u8> {
+    pub fn encode_indexed_literal(&mut self, index: u64, value: &str, index_bit: u8) -> Vec<u8> {
         let mut prefix: Vec<u8>;
         if index_bit == Encoder::INDEX_INCREMENTAL {
             prefix = self.encode_int(index, 6);
@@ -116,19 +282,108 @@ impl Encoder {
         }
 
         prefix[0] |= index_bit;
-        prefix.append(&mut self.encode_int(value.len() as u64, 7));
-        prefix.append(&mut value.as_bytes().to_vec());
+        prefix.append(&mut self.encode_string(value));
         prefix
     }
 
-    pub(crate) fn header_table_size(&self) -> usize {
-        self.header_table.max_size
+    pub fn snapshot(&self) -> EncoderSnapshot {
+        EncoderSnapshot {
+            table: self.header_table.snapshot(),
+            pending_table_sizes: self.header_table_changes.clone(),
+        }
+    }
+
+    // Sizes are checked against max_header_table_size, the observer is kept.
+    pub fn restore(&mut self, snapshot: &EncoderSnapshot) -> Result<(), SnapshotError> {
+        let sizes = snapshot.pending_table_sizes.iter().chain([&snapshot.table.max_size]);
+        if sizes.into_iter().any(|x| *x > self.max_header_table_size) {
+            return Err(SnapshotError::ExceedsLimit);
+        }
+
+        let mut table = HeaderTable::restore(&snapshot.table)?;
+        if let Some(observer) = self.header_table.take_observer() {
+            table.set_observer(observer);
+        }
+        self.header_table = table;
+        self.header_table_changes = snapshot.pending_table_sizes.clone();
+        self.eviction_base = 0;
+        Ok(())
+    }
+
+    pub fn header_table(&self) -> &HeaderTable {
+        &self.header_table
+    }
+
+    pub fn set_observer(&mut self, observer: Box<dyn TableObserver + Send>) {
+        self.header_table.set_observer(observer);
+    }
+
+    pub fn take_observer(&mut self) -> Option<Box<dyn TableObserver + Send>> {
+        self.header_table.take_observer()
     }
 
-    pub(crate) fn set_header_table_size(&mut self, size: usize) {
-        if self.header_table.max_size == size { return; }
+    pub fn header_table_size(&self) -> usize {
+        self.header_table.max_size()
+    }
+
+    pub fn set_header_table_size(&mut self, size: usize) {
+        let size = size.min(self.max_header_table_size);
+        if self.header_table.max_size() == size { return; }
 
         self.header_table_changes.push(size);
         self.header_table.set_max_size(size);
     }
+
+    pub fn max_header_table_size(&self) -> usize {
+        self.max_header_table_size
+    }
+
+    pub fn set_max_header_table_size(&mut self, size: usize) {
+        self.max_header_table_size = size;
+        if self.header_table.max_size() > size {
+            self.set_header_table_size(size);
+        }
+    }
+
+    pub fn huffman(&self) -> HuffmanPolicy {
+        self.huffman
+    }
+
+    pub fn set_huffman(&mut self, policy: HuffmanPolicy) {
+        self.huffman = policy;
+    }
+
+    pub fn indexing(&self) -> IndexingStrategy {
+        self.indexing
+    }
+
+    pub fn set_indexing(&mut self, strategy: IndexingStrategy) {
+        self.indexing = strategy;
+    }
+
+    pub fn validation(&self) -> Validation {
+        self.validation
+    }
+
+    pub fn set_validation(&mut self, validation: Validation) {
+        self.validation = validation;
+    }
+
+    pub fn stats(&self) -> CompressionStats {
+        CompressionStats {
+            evictions: self.header_table.eviction_count().saturating_sub(self.eviction_base),
+            ..self.stats
+        }
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = CompressionStats::default();
+        self.eviction_base = self.header_table.eviction_count();
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
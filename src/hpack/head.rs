@@ -0,0 +1,234 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::hpack::decoder::{Decoder, DecoderError};
+use crate::hpack::encoder::Encoder;
+use crate::hpack::header::Header;
+use crate::hpack::validation::{self, Validation, ValidationError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadError {
+    Malformed(ValidationError),
+    Decoder(DecoderError),
+    MissingPseudoHeader(&'static str),
+    UnexpectedPseudoHeader(String),
+    InvalidStatus(String),
+}
+
+impl fmt::Display for HeadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadError::Malformed(e) => write!(f, "Malformed header list: {}", e),
+            HeadError::Decoder(e) => write!(f, "Decode failed: {}", e),
+            HeadError::MissingPseudoHeader(x) => write!(f, "Missing pseudo-header: {}", x),
+            HeadError::UnexpectedPseudoHeader(x) => write!(f, "Unexpected pseudo-header: {}", x),
+            HeadError::InvalidStatus(x) => write!(f, "Invalid status: {}", x),
+        }
+    }
+}
+
+impl core::error::Error for HeadError {}
+
+impl From<ValidationError> for HeadError {
+    fn from(e: ValidationError) -> Self {
+        HeadError::Malformed(e)
+    }
+}
+
+impl From<DecoderError> for HeadError {
+    fn from(e: DecoderError) -> Self {
+        match e {
+            DecoderError::Malformed(x) => HeadError::Malformed(x),
+            x => HeadError::Decoder(x),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestHead {
+    pub method: String,
+    pub scheme: Option<String>,
+    pub authority: Option<String>,
+    pub path: Option<String>,
+    // RFC 8441 extended CONNECT
+    pub protocol: Option<String>,
+    pub headers: Vec<Header>,
+}
+
+impl RequestHead {
+
+    pub fn new(method: &str, scheme: &str, authority: &str, path: &str) -> Self {
+        RequestHead {
+            method: method.to_string(),
+            scheme: Some(scheme.to_string()),
+            authority: Some(authority.to_string()),
+            path: Some(path.to_string()),
+            protocol: None,
+            headers: vec![],
+        }
+    }
+
+    pub fn connect(authority: &str) -> Self {
+        RequestHead {
+            method: "CONNECT".to_string(),
+            scheme: None,
+            authority: Some(authority.to_string()),
+            path: None,
+            protocol: None,
+            headers: vec![],
+        }
+    }
+
+    pub fn from_headers(headers: Vec<Header>) -> Result<Self, HeadError> {
+        validation::validate(&headers, Validation::Strict)?;
+
+        let mut head = RequestHead {
+            method: String::new(),
+            scheme: None,
+            authority: None,
+            path: None,
+            protocol: None,
+            headers: vec![],
+        };
+        let mut method = None;
+
+        for header in headers {
+            match header.name.as_str() {
+                ":method" => method = Some(header.value),
+                ":scheme" => head.scheme = Some(header.value),
+                ":authority" => head.authority = Some(header.value),
+                ":path" => head.path = Some(header.value),
+                ":protocol" => head.protocol = Some(header.value),
+                ":status" => return Err(HeadError::UnexpectedPseudoHeader(header.name.to_string())),
+                _ => head.headers.push(header),
+            }
+        }
+
+        head.method = method.ok_or(HeadError::MissingPseudoHeader(":method"))?;
+        head.check()?;
+        Ok(head)
+    }
+
+    pub fn to_headers(&self) -> Result<Vec<Header>, HeadError> {
+        self.check()?;
+
+        let pseudo_headers = [
+            (":method", Some(&self.method)),
+            (":scheme", self.scheme.as_ref()),
+            (":authority", self.authority.as_ref()),
+            (":path", self.path.as_ref()),
+            (":protocol", self.protocol.as_ref()),
+        ];
+        let mut headers: Vec<Header> = pseudo_headers.iter()
+            .filter_map(|(name, value)| {
+                value.map(|x| Header {name: (*name).into(), value: x.clone()})
+            })
+            .collect();
+        headers.extend(self.headers.iter().cloned());
+
+        validation::validate(&headers, Validation::Strict)?;
+        Ok(headers)
+    }
+
+    pub fn encode(&self, encoder: &mut Encoder) -> Result<Vec<u8>, HeadError> {
+        Ok(encoder.encode(&self.to_headers()?))
+    }
+
+    pub fn decode(decoder: &mut Decoder, data: &[u8]) -> Result<Self, HeadError> {
+        RequestHead::from_headers(decoder.decode(data)?)
+    }
+
+    // RFC 7540 section 8.1.2.3 and 8.3, RFC 8441 section 4
+    fn check(&self) -> Result<(), HeadError> {
+        if self.method.is_empty() {
+            return Err(HeadError::MissingPseudoHeader(":method"));
+        }
+
+        let is_connect = self.method == "CONNECT";
+        if self.protocol.is_some() && !is_connect {
+            return Err(HeadError::UnexpectedPseudoHeader(":protocol".to_string()));
+        }
+
+        if is_connect && self.protocol.is_none() {
+            if self.scheme.is_some() {
+                return Err(HeadError::UnexpectedPseudoHeader(":scheme".to_string()));
+            }
+            if self.path.is_some() {
+                return Err(HeadError::UnexpectedPseudoHeader(":path".to_string()));
+            }
+            if self.authority.as_ref().is_none_or(|x| x.is_empty()) {
+                return Err(HeadError::MissingPseudoHeader(":authority"));
+            }
+            return Ok(());
+        }
+
+        if self.scheme.is_none() {
+            return Err(HeadError::MissingPseudoHeader(":scheme"));
+        }
+        if self.path.as_ref().is_none_or(|x| x.is_empty()) {
+            return Err(HeadError::MissingPseudoHeader(":path"));
+        }
+        if self.protocol.is_some() && self.authority.as_ref().is_none_or(|x| x.is_empty()) {
+            return Err(HeadError::MissingPseudoHeader(":authority"));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseHead {
+    pub status: u16,
+    pub headers: Vec<Header>,
+}
+
+impl ResponseHead {
+
+    pub fn new(status: u16) -> Self {
+        ResponseHead {status, headers: vec![]}
+    }
+
+    pub fn from_headers(headers: Vec<Header>) -> Result<Self, HeadError> {
+        validation::validate(&headers, Validation::Strict)?;
+
+        let mut status = None;
+        let mut regular = vec![];
+
+        for header in headers {
+            match header.name.as_str() {
+                ":status" => status = Some(header.value),
+                x if x.starts_with(':') => return Err(HeadError::UnexpectedPseudoHeader(x.to_string())),
+                _ => regular.push(header),
+            }
+        }
+
+        let status = status.ok_or(HeadError::MissingPseudoHeader(":status"))?;
+        let code = match status.parse::<u16>() {
+            Ok(x) if status.len() == 3 && (100..=999).contains(&x) => x,
+            _ => return Err(HeadError::InvalidStatus(status)),
+        };
+
+        Ok(ResponseHead {status: code, headers: regular})
+    }
+
+    pub fn to_headers(&self) -> Result<Vec<Header>, HeadError> {
+        if !(100..=999).contains(&self.status) {
+            return Err(HeadError::InvalidStatus(self.status.to_string()));
+        }
+
+        let mut headers = vec![Header {name: ":status".into(), value: self.status.to_string()}];
+        headers.extend(self.headers.iter().cloned());
+
+        validation::validate(&headers, Validation::Strict)?;
+        Ok(headers)
+    }
+
+    pub fn encode(&self, encoder: &mut Encoder) -> Result<Vec<u8>, HeadError> {
+        Ok(encoder.encode(&self.to_headers()?))
+    }
+
+    pub fn decode(decoder: &mut Decoder, data: &[u8]) -> Result<Self, HeadError> {
+        ResponseHead::from_headers(decoder.decode(data)?)
+    }
+}
@@ -1,12 +1,249 @@
-#[derive(Debug, Clone)]
-pub(crate) struct Header {
-    pub(crate) name: String,
-    pub(crate) value: String,
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::ops::Deref;
+
+// distinct names of the RFC 7541 Appendix A static table, in table order
+pub(crate) const STATIC_NAMES: [&str; 52] = [
+    ":authority",
+    ":method",
+    ":path",
+    ":scheme",
+    ":status",
+    "accept-charset",
+    "accept-encoding",
+    "accept-language",
+    "accept-ranges",
+    "accept",
+    "access-control-allow-origin",
+    "age",
+    "allow",
+    "authorization",
+    "cache-control",
+    "content-disposition",
+    "content-encoding",
+    "content-language",
+    "content-length",
+    "content-location",
+    "content-range",
+    "content-type",
+    "cookie",
+    "date",
+    "etag",
+    "expect",
+    "expires",
+    "from",
+    "host",
+    "if-match",
+    "if-modified-since",
+    "if-none-match",
+    "if-range",
+    "if-unmodified-since",
+    "last-modified",
+    "link",
+    "location",
+    "max-forwards",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "range",
+    "referer",
+    "refresh",
+    "retry-after",
+    "server",
+    "set-cookie",
+    "strict-transport-security",
+    "transfer-encoding",
+    "user-agent",
+    "vary",
+    "via",
+    "www-authenticate",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Repr {
+    Static(u8),
+    Custom(String),
+}
+
+// A header name that only allocates when it is not in the static table.
+// Static names are never stored as Custom, so derived equality holds.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HeaderName(Repr);
+
+impl HeaderName {
+
+    pub(crate) const fn from_static_index(index: u8) -> Self {
+        HeaderName(Repr::Static(index))
+    }
+
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Static(x) => STATIC_NAMES[*x as usize],
+            Repr::Custom(x) => x.as_str(),
+        }
+    }
+
+    // position in the list of distinct static table names
+    pub fn static_index(&self) -> Option<usize> {
+        match &self.0 {
+            Repr::Static(x) => Some(*x as usize),
+            Repr::Custom(_) => None,
+        }
+    }
+
+    pub fn lookup_static(name: &str) -> Option<usize> {
+        match name {
+            ":authority" => Some(0),
+            ":method" => Some(1),
+            ":path" => Some(2),
+            ":scheme" => Some(3),
+            ":status" => Some(4),
+            "accept-charset" => Some(5),
+            "accept-encoding" => Some(6),
+            "accept-language" => Some(7),
+            "accept-ranges" => Some(8),
+            "accept" => Some(9),
+            "access-control-allow-origin" => Some(10),
+            "age" => Some(11),
+            "allow" => Some(12),
+            "authorization" => Some(13),
+            "cache-control" => Some(14),
+            "content-disposition" => Some(15),
+            "content-encoding" => Some(16),
+            "content-language" => Some(17),
+            "content-length" => Some(18),
+            "content-location" => Some(19),
+            "content-range" => Some(20),
+            "content-type" => Some(21),
+            "cookie" => Some(22),
+            "date" => Some(23),
+            "etag" => Some(24),
+            "expect" => Some(25),
+            "expires" => Some(26),
+            "from" => Some(27),
+            "host" => Some(28),
+            "if-match" => Some(29),
+            "if-modified-since" => Some(30),
+            "if-none-match" => Some(31),
+            "if-range" => Some(32),
+            "if-unmodified-since" => Some(33),
+            "last-modified" => Some(34),
+            "link" => Some(35),
+            "location" => Some(36),
+            "max-forwards" => Some(37),
+            "proxy-authenticate" => Some(38),
+            "proxy-authorization" => Some(39),
+            "range" => Some(40),
+            "referer" => Some(41),
+            "refresh" => Some(42),
+            "retry-after" => Some(43),
+            "server" => Some(44),
+            "set-cookie" => Some(45),
+            "strict-transport-security" => Some(46),
+            "transfer-encoding" => Some(47),
+            "user-agent" => Some(48),
+            "vary" => Some(49),
+            "via" => Some(50),
+            "www-authenticate" => Some(51),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
-pub(crate) struct EncodableHeader {
-    pub(crate) name: String,
-    pub(crate) value: String,
-    pub(crate) is_sensitive: bool,
+impl From<&str> for HeaderName {
+    fn from(name: &str) -> Self {
+        match HeaderName::lookup_static(name) {
+            Some(x) => HeaderName(Repr::Static(x as u8)),
+            None => HeaderName(Repr::Custom(name.to_string())),
+        }
+    }
+}
+
+impl From<String> for HeaderName {
+    fn from(name: String) -> Self {
+        match HeaderName::lookup_static(&name) {
+            Some(x) => HeaderName(Repr::Static(x as u8)),
+            None => HeaderName(Repr::Custom(name)),
+        }
+    }
+}
+
+impl Deref for HeaderName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for HeaderName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for HeaderName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for HeaderName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(HeaderName::from)
+    }
+}
+
+impl PartialEq<str> for HeaderName {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for HeaderName {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for HeaderName {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Header {
+    pub name: HeaderName,
+    pub value: String,
+}
+
+impl Header {
+
+    pub fn new(name: impl Into<HeaderName>, value: impl Into<String>) -> Self {
+        Header {name: name.into(), value: value.into()}
+    }
+
+    // RFC 7541 section 4.1 / RFC 7540 section 6.5.2: name and value octets plus 32
+    pub fn size(&self) -> usize {
+        self.name.len() + self.value.len() + 32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EncodableHeader {
+    pub name: HeaderName,
+    pub value: String,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "core::ops::Not::not"))]
+    pub is_sensitive: bool,
+}
+
+impl EncodableHeader {
+
+    pub fn new(name: impl Into<HeaderName>, value: impl Into<String>, is_sensitive: bool) -> Self {
+        EncodableHeader {name: name.into(), value: value.into(), is_sensitive}
+    }
 }
@@ -1,145 +1,390 @@
-use std::ops::Index;
-use lazy_static::lazy_static;
-use crate::hpack::header::Header;
-
-lazy_static! {
-    static ref STATIC_ENTRIES: [Header; 61] = [
-        Header {name: ":authority".to_string(), value: "".to_string()},
-        Header {name: ":method".to_string(), value: "GET".to_string()},
-        Header {name: ":method".to_string(), value: "POST".to_string()},
-        Header {name: ":path".to_string(), value: "/".to_string()},
-        Header {name: ":path".to_string(), value: "/index.html".to_string()},
-        Header {name: ":scheme".to_string(), value: "http".to_string()},
-        Header {name: ":scheme".to_string(), value: "https".to_string()},
-        Header {name: ":status".to_string(), value: "200".to_string()},
-        Header {name: ":status".to_string(), value: "204".to_string()},
-        Header {name: ":status".to_string(), value: "206".to_string()},
-        Header {name: ":status".to_string(), value: "304".to_string()},
-        Header {name: ":status".to_string(), value: "400".to_string()},
-        Header {name: ":status".to_string(), value: "404".to_string()},
-        Header {name: ":status".to_string(), value: "500".to_string()},
-        Header {name: "accept-charset".to_string(), value: "".to_string()},
-        Header {name: "accept-encoding".to_string(), value: "gzip, deflate".to_string()},
-        Header {name: "accept-language".to_string(), value: "".to_string()},
-        Header {name: "accept-ranges".to_string(), value: "".to_string()},
-        Header {name: "accept".to_string(), value: "".to_string()},
-        Header {name: "access-control-allow-origin".to_string(), value: "".to_string()},
-        Header {name: "age".to_string(), value: "".to_string()},
-        Header {name: "allow".to_string(), value: "".to_string()},
-        Header {name: "authorization".to_string(), value: "".to_string()},
-        Header {name: "cache-control".to_string(), value: "".to_string()},
-        Header {name: "content-disposition".to_string(), value: "".to_string()},
-        Header {name: "content-encoding".to_string(), value: "".to_string()},
-        Header {name: "content-language".to_string(), value: "".to_string()},
-        Header {name: "content-length".to_string(), value: "".to_string()},
-        Header {name: "content-location".to_string(), value: "".to_string()},
-        Header {name: "content-range".to_string(), value: "".to_string()},
-        Header {name: "content-type".to_string(), value: "".to_string()},
-        Header {name: "cookie".to_string(), value: "".to_string()},
-        Header {name: "date".to_string(), value: "".to_string()},
-        Header {name: "etag".to_string(), value: "".to_string()},
-        Header {name: "expect".to_string(), value: "".to_string()},
-        Header {name: "expires".to_string(), value: "".to_string()},
-        Header {name: "from".to_string(), value: "".to_string()},
-        Header {name: "host".to_string(), value: "".to_string()},
-        Header {name: "if-match".to_string(), value: "".to_string()},
-        Header {name: "if-modified-since".to_string(), value: "".to_string()},
-        Header {name: "if-none-match".to_string(), value: "".to_string()},
-        Header {name: "if-range".to_string(), value: "".to_string()},
-        Header {name: "if-unmodified-since".to_string(), value: "".to_string()},
-        Header {name: "last-modified".to_string(), value: "".to_string()},
-        Header {name: "link".to_string(), value: "".to_string()},
-        Header {name: "location".to_string(), value: "".to_string()},
-        Header {name: "max-forwards".to_string(), value: "".to_string()},
-        Header {name: "proxy-authenticate".to_string(), value: "".to_string()},
-        Header {name: "proxy-authorization".to_string(), value: "".to_string()},
-        Header {name: "range".to_string(), value: "".to_string()},
-        Header {name: "referer".to_string(), value: "".to_string()},
-        Header {name: "refresh".to_string(), value: "".to_string()},
-        Header {name: "retry-after".to_string(), value: "".to_string()},
-        Header {name: "server".to_string(), value: "".to_string()},
-        Header {name: "set-cookie".to_string(), value: "".to_string()},
-        Header {name: "strict-transport-security".to_string(), value: "".to_string()},
-        Header {name: "transfer-encoding".to_string(), value: "".to_string()},
-        Header {name: "user-agent".to_string(), value: "".to_string()},
-        Header {name: "vary".to_string(), value: "".to_string()},
-        Header {name: "via".to_string(), value: "".to_string()},
-        Header {name: "www-authenticate".to_string(), value: "".to_string()},
-    ];
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::hpack::header::{Header, HeaderName, STATIC_NAMES};
+
+pub const STATIC_TABLE_LEN: usize = 61;
+
+// RFC 7541 Appendix A
+const STATIC_ENTRIES: [(&str, &str); STATIC_TABLE_LEN] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+// static name index of every entry, the names appear in STATIC_NAMES order
+const STATIC_NAME_INDICES: [u8; STATIC_TABLE_LEN] = {
+    let mut indices = [0; STATIC_TABLE_LEN];
+    let mut name = 0;
+    let mut i = 0;
+    while i < STATIC_TABLE_LEN {
+        if !str_eq(STATIC_ENTRIES[i].0, STATIC_NAMES[name]) {
+            name += 1;
+        }
+        assert!(str_eq(STATIC_ENTRIES[i].0, STATIC_NAMES[name]));
+        indices[i] = name as u8;
+        i += 1;
+    }
+    indices
+};
+
+// static table index range of every distinct static name
+const STATIC_NAME_RANGES: [(usize, usize); STATIC_NAMES.len()] = {
+    let mut ranges = [(0, 0); STATIC_NAMES.len()];
+    let mut i = 0;
+    while i < STATIC_TABLE_LEN {
+        let name = STATIC_NAME_INDICES[i] as usize;
+        if ranges[name].0 == 0 {
+            ranges[name].0 = i + 1;
+        }
+        ranges[name].1 = i + 1;
+        i += 1;
+    }
+    ranges
+};
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    SizeMismatch,
+    ExceedsMaxSize,
+    // the table is larger than the restoring side allows
+    ExceedsLimit,
 }
 
-pub(crate) struct HeaderTable {
-    dynamic_entries: Vec<Header>,
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            SnapshotError::SizeMismatch => "Snapshot size does not match its entries!",
+            SnapshotError::ExceedsMaxSize => "Snapshot size exceeds its max size!",
+            SnapshotError::ExceedsLimit => "Snapshot max size exceeds the header table size limit!",
+        };
+        f.write_str(message)
+    }
+}
+
+impl core::error::Error for SnapshotError {}
+
+// Dynamic table state, entries are newest first like their indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableSnapshot {
+    pub entries: Vec<Header>,
+    pub size: usize,
     pub max_size: usize,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableEntry<'a> {
+    // HPACK index, 62 for the newest entry
+    pub index: usize,
+    // insertion number, stable for the lifetime of the entry
+    pub absolute_index: u64,
+    pub header: &'a Header,
+}
+
+// Notified of dynamic table changes, e.g. for churn metrics or reference tracking.
+pub trait TableObserver {
+    fn on_insert(&mut self, _entry: TableEntry<'_>) {}
+
+    // the entry as it was just before eviction
+    fn on_evict(&mut self, _entry: TableEntry<'_>) {}
+
+    fn on_resize(&mut self, _old_max_size: usize, _new_max_size: usize) {}
+}
+
+pub struct HeaderTable {
+    // newest entry first, so position i has index STATIC_TABLE_LEN + i + 1
+    dynamic_entries: VecDeque<Header>,
+    size: usize,
+    insert_count: u64,
+    eviction_count: u64,
+    observer: Option<Box<dyn TableObserver + Send>>,
+    max_size: usize,
+}
+
 impl HeaderTable {
 
-    pub(crate) fn new(max_size: usize) -> HeaderTable {
+    pub fn new(max_size: usize) -> HeaderTable {
         HeaderTable{
-            dynamic_entries: Vec::new(),
+            dynamic_entries: VecDeque::new(),
+            size: 0,
+            insert_count: 0,
+            eviction_count: 0,
+            observer: None,
             max_size
         }
     }
 
-    pub(crate) fn new_default() -> HeaderTable {
-        HeaderTable{
-            dynamic_entries: Vec::new(),
-            max_size: 4096
+    pub fn new_default() -> HeaderTable {
+        HeaderTable::new(4096)
+    }
+
+    pub fn restore(snapshot: &TableSnapshot) -> Result<HeaderTable, SnapshotError> {
+        let size: usize = snapshot.entries.iter().map(|x| x.size()).sum();
+        if size != snapshot.size {
+            return Err(SnapshotError::SizeMismatch);
+        }
+        if size > snapshot.max_size {
+            return Err(SnapshotError::ExceedsMaxSize);
         }
+
+        Ok(HeaderTable {
+            dynamic_entries: snapshot.entries.iter().cloned().collect(),
+            size,
+            insert_count: snapshot.entries.len() as u64,
+            eviction_count: 0,
+            observer: None,
+            max_size: snapshot.max_size,
+        })
     }
 
-    pub(crate) fn search_with_name(&self, name: &str) -> Option<usize> {
-        for (i, header) in STATIC_ENTRIES.iter().enumerate() {
-            if header.name != name { continue; }
-            return Some(i + 1);
+    pub fn snapshot(&self) -> TableSnapshot {
+        TableSnapshot {
+            entries: self.dynamic_entries.iter().cloned().collect(),
+            size: self.size,
+            max_size: self.max_size,
+        }
+    }
+
+    // current dynamic table size in octets
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.dynamic_entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dynamic_entries.is_empty()
+    }
+
+    pub fn insert_count(&self) -> u64 {
+        self.insert_count
+    }
+
+    pub fn eviction_count(&self) -> u64 {
+        self.eviction_count
+    }
+
+    // dynamic entries, newest first
+    pub fn entries(&self) -> impl Iterator<Item = TableEntry<'_>> {
+        self.dynamic_entries.iter().enumerate().map(move |(i, header)| TableEntry {
+            index: STATIC_TABLE_LEN + i + 1,
+            absolute_index: self.insert_count - i as u64 - 1,
+            header,
+        })
+    }
+
+    pub fn set_observer(&mut self, observer: Box<dyn TableObserver + Send>) {
+        self.observer = Some(observer);
+    }
+
+    pub fn take_observer(&mut self) -> Option<Box<dyn TableObserver + Send>> {
+        self.observer.take()
+    }
+
+    // static entries are built on demand, dynamic ones borrowed
+    pub fn get(&self, index: usize) -> Option<Cow<'_, Header>> {
+        match index {
+            0 => None,
+            x if x <= STATIC_TABLE_LEN => {
+                let name = HeaderName::from_static_index(STATIC_NAME_INDICES[x - 1]);
+                Some(Cow::Owned(Header {name, value: STATIC_ENTRIES[x - 1].1.to_string()}))
+            }
+            x => self.dynamic_entries.get(x - STATIC_TABLE_LEN - 1).map(Cow::Borrowed),
+        }
+    }
+
+    // the name alone, without building the value of a static entry
+    pub fn get_name(&self, index: usize) -> Option<HeaderName> {
+        match index {
+            0 => None,
+            x if x <= STATIC_TABLE_LEN => Some(HeaderName::from_static_index(STATIC_NAME_INDICES[x - 1])),
+            x => self.dynamic_entries.get(x - STATIC_TABLE_LEN - 1).map(|x| x.name.clone()),
+        }
+    }
+
+    pub fn search_with_name(&self, name: &str) -> Option<usize> {
+        self.find_name(HeaderName::lookup_static(name), name)
+    }
+
+    // skips the static name lookup for an already interned name
+    pub fn search_with_header_name(&self, name: &HeaderName) -> Option<usize> {
+        self.find_name(name.static_index(), name)
+    }
+
+    pub fn search_with_name_and_value(&self, name: &str, value: &str) -> Option<usize> {
+        self.find(HeaderName::lookup_static(name), name, value)
+    }
+
+    pub fn search_with_header_name_and_value(&self, name: &HeaderName, value: &str) -> Option<usize> {
+        self.find(name.static_index(), name, value)
+    }
+
+    fn find_name(&self, static_name: Option<usize>, name: &str) -> Option<usize> {
+        // custom names can only live in the dynamic table
+        if let Some(x) = static_name {
+            return Some(STATIC_NAME_RANGES[x].0);
         }
 
         for (i, header) in self.dynamic_entries.iter().enumerate() {
             if header.name != name { continue; }
-            return Some(STATIC_ENTRIES.len() + i + 1);
+            return Some(STATIC_TABLE_LEN + i + 1);
         }
 
         None
     }
 
-    pub(crate) fn search_with_name_and_value(&self, name: &str, value: &str) -> Option<usize> {
-        for (i, header) in STATIC_ENTRIES.iter().enumerate() {
-            if header.name != name || header.value != value { continue; }
-            return Some(i + 1);
+    fn find(&self, static_name: Option<usize>, name: &str, value: &str) -> Option<usize> {
+        if let Some(x) = static_name {
+            let (first, last) = STATIC_NAME_RANGES[x];
+            for i in first..=last {
+                if STATIC_ENTRIES[i - 1].1 != value { continue; }
+                return Some(i);
+            }
         }
 
         for (i, header) in self.dynamic_entries.iter().enumerate() {
-            if  header.name != name || header.value != value { continue; }
-            return Some(STATIC_ENTRIES.len() + i + 1);
+            let name_matches = match static_name {
+                Some(x) => header.name.static_index() == Some(x),
+                None => header.name == name,
+            };
+            if !name_matches || header.value != value { continue; }
+            return Some(STATIC_TABLE_LEN + i + 1);
         }
 
         None
     }
 
-    pub(crate) fn add(&mut self, name: &str, value: &str) {
-        self.dynamic_entries.push(Header{name: name.to_string(), value: value.to_string()});
+    // RFC 7541 section 4.4: an entry larger than the table empties it
+    pub fn add(&mut self, name: &str, value: &str) {
+        self.add_header(Header{name: name.into(), value: value.to_string()});
     }
 
-    pub(crate) fn set_max_size(&mut self, size: usize) {
-        if size == 0 {
-            self.dynamic_entries.clear()
-        } else if self.dynamic_entries.len() > size {
-            self.dynamic_entries = self.dynamic_entries[0..size].to_owned();
+    pub fn add_header(&mut self, header: Header) {
+        let entry_size = header.size();
+        if entry_size > self.max_size {
+            self.evict(0);
+            return;
         }
 
+        self.evict(self.max_size - entry_size);
+        self.size += entry_size;
+        self.insert_count += 1;
+        self.dynamic_entries.push_front(header);
+
+        if let Some(observer) = &mut self.observer {
+            observer.on_insert(TableEntry {
+                index: STATIC_TABLE_LEN + 1,
+                absolute_index: self.insert_count - 1,
+                header: &self.dynamic_entries[0],
+            });
+        }
+    }
+
+    pub fn set_max_size(&mut self, size: usize) {
+        let old_max_size = self.max_size;
         self.max_size = size;
+        if let Some(observer) = &mut self.observer {
+            observer.on_resize(old_max_size, size);
+        }
+
+        self.evict(size);
     }
-}
 
-impl Index<usize> for HeaderTable {
-    type Output = Header;
+    fn evict(&mut self, size: usize) {
+        while self.size > size {
+            let index = STATIC_TABLE_LEN + self.dynamic_entries.len();
+            let absolute_index = self.insert_count - self.dynamic_entries.len() as u64;
+            let Some(header) = self.dynamic_entries.pop_back() else { break };
+            self.size -= header.size();
+            self.eviction_count += 1;
 
-    fn index(&self, index: usize) -> &Self::Output {
-        if index <= STATIC_ENTRIES.len() {
-            return &STATIC_ENTRIES[index - 1];
+            if let Some(observer) = &mut self.observer {
+                observer.on_evict(TableEntry {index, absolute_index, header: &header});
+            }
         }
-
-        &self.dynamic_entries[index - STATIC_ENTRIES.len() - 1]
     }
 }
@@ -0,0 +1,50 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexError {
+    OddLength,
+    InvalidDigit(char),
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexError::OddLength => f.write_str("Odd number of hex digits!"),
+            HexError::InvalidDigit(x) => write!(f, "Invalid hex digit {:?}!", x),
+        }
+    }
+}
+
+impl core::error::Error for HexError {}
+
+// lowercase, two digits per byte
+pub fn encode(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for x in bytes {
+        hex.push(DIGITS[(x >> 4) as usize] as char);
+        hex.push(DIGITS[(x & 0xf) as usize] as char);
+    }
+    hex
+}
+
+// like encode, with a space between bytes, e.g. "82 86 84"
+pub fn encode_spaced(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| encode(&[*x])).collect::<Vec<String>>().join(" ")
+}
+
+// whitespace between digits is ignored, e.g. "82 86 84"
+pub fn decode(text: &str) -> Result<Vec<u8>, HexError> {
+    let nibbles = text.chars()
+        .filter(|x| !x.is_ascii_whitespace())
+        .map(|x| x.to_digit(16).map(|n| n as u8).ok_or(HexError::InvalidDigit(x)))
+        .collect::<Result<Vec<u8>, HexError>>()?;
+    if !nibbles.len().is_multiple_of(2) {
+        return Err(HexError::OddLength);
+    }
+
+    Ok(nibbles.chunks(2).map(|x| x[0] << 4 | x[1]).collect())
+}
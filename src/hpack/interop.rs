@@ -0,0 +1,157 @@
+use core::fmt;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use http::{request, response, Request, Response, Uri};
+use crate::hpack::head::{HeadError, RequestHead, ResponseHead};
+use crate::hpack::header::{EncodableHeader, Header};
+use crate::hpack::validation::{self, Validation};
+
+#[derive(Debug)]
+pub enum InteropError {
+    Head(HeadError),
+    Http(http::Error),
+    NonUtf8Value(String),
+}
+
+impl fmt::Display for InteropError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InteropError::Head(e) => write!(f, "{}", e),
+            InteropError::Http(e) => write!(f, "{}", e),
+            InteropError::NonUtf8Value(x) => write!(f, "Non UTF-8 value for header: {}", x),
+        }
+    }
+}
+
+impl core::error::Error for InteropError {}
+
+impl From<HeadError> for InteropError {
+    fn from(e: HeadError) -> Self {
+        InteropError::Head(e)
+    }
+}
+
+impl<T: Into<http::Error>> From<T> for InteropError {
+    fn from(e: T) -> Self {
+        InteropError::Http(e.into())
+    }
+}
+
+// RFC 8441 :protocol pseudo-header, kept in the request extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Protocol(pub String);
+
+pub fn from_header_map(map: &HeaderMap) -> Result<Vec<EncodableHeader>, InteropError> {
+    map.iter()
+        .map(|(name, value)| {
+            let text = String::from_utf8(value.as_bytes().to_vec())
+                .map_err(|_| InteropError::NonUtf8Value(name.to_string()))?;
+
+            Ok(EncodableHeader {
+                name: name.as_str().into(),
+                value: text,
+                is_sensitive: value.is_sensitive(),
+            })
+        })
+        .collect()
+}
+
+pub fn to_header_map(headers: Vec<EncodableHeader>) -> Result<HeaderMap, InteropError> {
+    let mut map = HeaderMap::with_capacity(headers.len());
+
+    for header in headers {
+        let name = HeaderName::from_bytes(header.name.as_bytes())?;
+        let mut value = HeaderValue::from_str(&header.value)?;
+        value.set_sensitive(header.is_sensitive);
+        map.append(name, value);
+    }
+
+    Ok(map)
+}
+
+pub fn from_request_parts(parts: &request::Parts) -> Result<Vec<EncodableHeader>, InteropError> {
+    let protocol = parts.extensions.get::<Protocol>();
+    let authority = parts.uri.authority().map(|x| x.as_str());
+
+    let head = if parts.method == http::Method::CONNECT && protocol.is_none() {
+        RequestHead::connect(authority.ok_or(HeadError::MissingPseudoHeader(":authority"))?)
+    } else {
+        RequestHead {
+            method: parts.method.to_string(),
+            scheme: parts.uri.scheme_str().map(|x| x.to_string()),
+            authority: authority.map(|x| x.to_string()),
+            path: Some(parts.uri.path_and_query().map_or("/", |x| x.as_str()).to_string()),
+            protocol: protocol.map(|x| x.0.clone()),
+            headers: vec![],
+        }
+    };
+
+    let mut headers = into_encodable(head.to_headers()?);
+    headers.append(&mut from_header_map(&parts.headers)?);
+    Ok(headers)
+}
+
+pub fn to_request_parts(headers: Vec<EncodableHeader>) -> Result<request::Parts, InteropError> {
+    let (pseudo_headers, regular) = split_pseudo_headers(headers)?;
+    let head = RequestHead::from_headers(pseudo_headers)?;
+
+    let mut uri = Uri::builder();
+    if let Some(x) = &head.scheme {
+        uri = uri.scheme(x.as_str());
+    }
+    if let Some(x) = &head.authority {
+        uri = uri.authority(x.as_str());
+    }
+    if let Some(x) = &head.path {
+        uri = uri.path_and_query(x.as_str());
+    }
+
+    let mut request = Request::builder()
+        .method(head.method.as_str())
+        .uri(uri.build()?);
+    if let Some(x) = head.protocol {
+        request = request.extension(Protocol(x));
+    }
+
+    let (mut parts, _) = request.body(())?.into_parts();
+    parts.headers = to_header_map(regular)?;
+    Ok(parts)
+}
+
+pub fn from_response_parts(parts: &response::Parts) -> Result<Vec<EncodableHeader>, InteropError> {
+    let head = ResponseHead::new(parts.status.as_u16());
+
+    let mut headers = into_encodable(head.to_headers()?);
+    headers.append(&mut from_header_map(&parts.headers)?);
+    Ok(headers)
+}
+
+pub fn to_response_parts(headers: Vec<EncodableHeader>) -> Result<response::Parts, InteropError> {
+    let (pseudo_headers, regular) = split_pseudo_headers(headers)?;
+    let head = ResponseHead::from_headers(pseudo_headers)?;
+
+    let (mut parts, _) = Response::builder()
+        .status(head.status)
+        .body(())?
+        .into_parts();
+    parts.headers = to_header_map(regular)?;
+    Ok(parts)
+}
+
+fn into_encodable(headers: Vec<Header>) -> Vec<EncodableHeader> {
+    headers.into_iter()
+        .map(|x| EncodableHeader {name: x.name, value: x.value, is_sensitive: false})
+        .collect()
+}
+
+fn split_pseudo_headers(headers: Vec<EncodableHeader>) -> Result<(Vec<Header>, Vec<EncodableHeader>), InteropError> {
+    // checked on the whole list, ordering is lost once the pseudo-headers are split off
+    validation::validate_encodable(&headers, Validation::Strict).map_err(HeadError::Malformed)?;
+
+    let (pseudo_headers, regular): (Vec<EncodableHeader>, Vec<EncodableHeader>) = headers.into_iter()
+        .partition(|x| x.name.starts_with(':'));
+    let pseudo_headers = pseudo_headers.into_iter()
+        .map(|x| Header {name: x.name, value: x.value})
+        .collect();
+
+    Ok((pseudo_headers, regular))
+}
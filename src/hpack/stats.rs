@@ -0,0 +1,48 @@
+use crate::hpack::header_table::STATIC_TABLE_LEN;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    pub blocks: u64,
+    pub headers: u64,
+    // name and value octets of the header lists
+    pub raw_bytes: u64,
+    // octets of the header blocks
+    pub encoded_bytes: u64,
+    pub indexed: u64,
+    pub literal_incremental: u64,
+    pub literal_without_indexing: u64,
+    pub literal_never_indexed: u64,
+    pub table_size_updates: u64,
+    // indexed fields and indexed names, by the table they came from
+    pub static_hits: u64,
+    pub dynamic_hits: u64,
+    pub huffman_strings: u64,
+    // octets saved by Huffman coding, on top of encoded_bytes
+    pub huffman_saved_bytes: u64,
+    pub evictions: u64,
+}
+
+impl CompressionStats {
+
+    // encoded_bytes / raw_bytes, 1.0 before anything is counted
+    pub fn compression_ratio(&self) -> f64 {
+        if self.raw_bytes == 0 {
+            return 1.0;
+        }
+
+        self.encoded_bytes as f64 / self.raw_bytes as f64
+    }
+
+    pub(crate) fn record_index(&mut self, index: usize) {
+        if index <= STATIC_TABLE_LEN {
+            self.static_hits += 1;
+        } else {
+            self.dynamic_hits += 1;
+        }
+    }
+
+    pub(crate) fn record_huffman(&mut self, raw_len: usize, encoded_len: usize) {
+        self.huffman_strings += 1;
+        self.huffman_saved_bytes += raw_len.saturating_sub(encoded_len) as u64;
+    }
+}
@@ -0,0 +1,142 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use crate::hpack::decoder::{Decoder, DecoderError};
+use crate::hpack::encoder::Encoder;
+use crate::hpack::header::Header;
+
+// Story format of https://github.com/http2jp/hpack-test-case, a sequence of
+// header blocks sharing one dynamic table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Story {
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "String::is_empty"))]
+    pub description: String,
+    pub cases: Vec<Case>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Case {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub seqno: usize,
+    // SETTINGS_HEADER_TABLE_SIZE acknowledged just before this case, 4096 if never set
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub header_table_size: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(with = "wire"))]
+    pub wire: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "header_list"))]
+    pub headers: Vec<Header>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoryError {
+    Decoder(usize, DecoderError),
+    Mismatch(usize, Vec<Header>),
+}
+
+impl fmt::Display for StoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoryError::Decoder(seqno, e) => write!(f, "Case {}: {}", seqno, e),
+            StoryError::Mismatch(seqno, _) => write!(f, "Case {}: decoded headers do not match!", seqno),
+        }
+    }
+}
+
+impl core::error::Error for StoryError {}
+
+impl Story {
+
+    // Encodes every header list in order, the first case records the
+    // encoder's table size so decoders start from the same settings.
+    pub fn generate(encoder: &mut Encoder, description: &str, header_lists: &[Vec<Header>]) -> Self {
+        let cases = header_lists.iter().enumerate()
+            .map(|(i, headers)| Case {
+                seqno: i,
+                header_table_size: if i == 0 { Some(encoder.max_header_table_size()) } else { None },
+                wire: encoder.encode(headers),
+                headers: headers.clone(),
+            })
+            .collect();
+
+        Story {description: description.to_string(), cases}
+    }
+
+    pub fn header_lists(&self) -> Vec<Vec<Header>> {
+        self.cases.iter().map(|x| x.headers.clone()).collect()
+    }
+
+    // Runs the cases through a fresh decoder, stopping at the first failure.
+    pub fn verify(&self) -> Result<(), StoryError> {
+        let mut decoder = Decoder::new();
+
+        for (i, case) in self.cases.iter().enumerate() {
+            if let Some(size) = case.header_table_size {
+                // no size update is needed before the first block
+                if i == 0 {
+                    decoder = Decoder::builder().max_header_table_size(size).build();
+                } else {
+                    decoder.set_max_header_table_size(size);
+                }
+            }
+
+            let headers = decoder.decode(&case.wire).map_err(|e| StoryError::Decoder(case.seqno, e))?;
+            if headers != case.headers {
+                return Err(StoryError::Mismatch(case.seqno, headers));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+mod wire {
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use crate::hpack::hex;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let wire = String::deserialize(deserializer)?;
+        hex::decode(&wire).map_err(|e| de::Error::custom(format!("invalid hex wire: {}: {}", wire, e)))
+    }
+}
+
+// headers are single entry objects, e.g. [{":method": "GET"}]
+#[cfg(feature = "serde")]
+mod header_list {
+    use alloc::collections::BTreeMap;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use serde::ser::SerializeSeq;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use crate::hpack::header::Header;
+
+    pub fn serialize<S: Serializer>(headers: &[Header], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(headers.len()))?;
+        for header in headers {
+            seq.serialize_element(&BTreeMap::from([(header.name.as_str(), header.value.as_str())]))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Header>, D::Error> {
+        let entries = Vec::<BTreeMap<String, String>>::deserialize(deserializer)?;
+        entries.into_iter()
+            .map(|entry| {
+                if entry.len() != 1 {
+                    return Err(de::Error::custom("header must be a single name value pair"));
+                }
+                let (name, value) = entry.into_iter().next().unwrap();
+                Ok(Header::new(name, value))
+            })
+            .collect()
+    }
+}
@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod encoder_tests {
-    use crate::hpack::encoder::Encoder;
+    use crate::hpack::decoder::Decoder;
+    use crate::hpack::encoder::{Encoder, HuffmanPolicy, IndexingStrategy};
     use crate::hpack::header::{EncodableHeader, Header};
 
     #[test]
@@ -33,7 +34,7 @@ mod encoder_tests {
     fn test_encode_indexed() {
         let mut encoder = Encoder::new();
         let headers = [
-            Header{name: ":method".to_string(), value: "GET".to_string()}
+            Header{name: ":method".into(), value: "GET".to_string()}
         ];
         let encoded = encoder.encode(&headers);
         assert_eq!(encoded.len(), 1);
@@ -45,14 +46,14 @@ mod encoder_tests {
         let mut encoder = Encoder::new();
         let headers = [
             EncodableHeader {
-                name: "foo".to_string(),
+                name: "foo".into(),
                 value: "bar".to_string(),
                 is_sensitive: true,
             }
         ];
         let encoded = encoder.encode_headers(&headers);
         let expected = vec![
-            0u8,
+            16u8,
             3,
             102, 111, 111,
             3,
@@ -62,12 +63,21 @@ mod encoder_tests {
         assert_eq!(encoded, expected);
     }
 
+    #[test]
+    fn test_encode_sensitive_indexed_match() {
+        let mut encoder = Encoder::new();
+        let headers = [EncodableHeader::new(":method", "GET", true)];
+
+        // never indexed with the static name, not the static entry 2
+        assert_eq!(encoder.encode_headers(&headers), vec![16 | 2, 3, 71, 69, 84]);
+    }
+
     #[test]
     fn test_encode_literal_indexing() {
         let mut encoder = Encoder::new();
         let path = "/sample/path";
         let headers = [
-            Header {name: ":path".to_string(), value: path.to_string()}
+            Header {name: ":path".into(), value: path.to_string()}
         ];
         let encoded = encoder.encode(&headers);
         let mut expected: Vec<u8> = vec![68, path.len().try_into().unwrap()];
@@ -76,7 +86,7 @@ mod encoder_tests {
         assert_eq!(encoded, expected);
 
         // adds to header table
-        let idx = encoder.header_table.search_with_name_and_value(":path", path);
+        let idx = encoder.header_table().search_with_name_and_value(":path", path);
         assert_eq!(idx.unwrap(), 62);
     }
 
@@ -86,7 +96,7 @@ mod encoder_tests {
         let path = "/sample/path";
         let headers = [
             EncodableHeader {
-                name: ":path".to_string(),
+                name: ":path".into(),
                 value: path.to_string(),
                 is_sensitive: true,
             }
@@ -98,15 +108,138 @@ mod encoder_tests {
         assert_eq!(encoded, expected);
 
         // doesn't add to header table
-        let idx = encoder.header_table.search_with_name_and_value(":path", path);
+        let idx = encoder.header_table().search_with_name_and_value(":path", path);
         assert!(idx.is_none());
     }
+
+    #[test]
+    fn test_encode_new_name_indexing() {
+        let mut encoder = Encoder::new();
+        let headers = [
+            Header {name: "custom-key".into(), value: "custom-value".to_string()}
+        ];
+        let encoded = encoder.encode(&headers);
+        let mut expected: Vec<u8> = vec![64, 10];
+        expected.append(&mut "custom-key".as_bytes().to_vec());
+        expected.push(12);
+        expected.append(&mut "custom-value".as_bytes().to_vec());
+
+        assert_eq!(encoded, expected);
+        assert_eq!(encoder.encode(&headers), vec![190]);
+    }
+
+    #[test]
+    fn test_encode_indexed_name_literal_indexing() {
+        let mut encoder = Encoder::new();
+        let headers = [
+            Header {name: ":authority".into(), value: "www.example.com".to_string()}
+        ];
+        let encoded = encoder.encode(&headers);
+        assert_eq!(encoded[0], 65);
+    }
+
+    #[test]
+    fn test_encode_without_indexing_strategy() {
+        let mut encoder = Encoder::builder()
+            .indexing(IndexingStrategy::WithoutIndexing)
+            .build();
+        let path = "/sample/path";
+        let headers = [
+            Header {name: ":path".into(), value: path.to_string()}
+        ];
+        let encoded = encoder.encode(&headers);
+        let mut expected = vec![4, path.len().try_into().unwrap()];
+        expected.append(&mut path.as_bytes().to_vec());
+
+        assert_eq!(encoded, expected);
+        assert!(encoder.header_table().search_with_name_and_value(":path", path).is_none());
+    }
+
+    #[test]
+    fn test_encode_huffman_always() {
+        let mut encoder = Encoder::builder().huffman(HuffmanPolicy::Always).build();
+        let headers = [
+            Header {name: ":authority".into(), value: "www.example.com".to_string()}
+        ];
+        let encoded = encoder.encode(&headers);
+        let expected = vec![65, 140, 241, 227, 194, 229, 242, 58, 107, 160, 171, 144, 244, 255];
+
+        assert_eq!(encoded, expected);
+    }
+
+    #[test]
+    fn test_encode_huffman_shortest() {
+        let mut encoder = Encoder::builder().huffman(HuffmanPolicy::Shortest).build();
+        // "aaa" takes 15 bits when Huffman coded, '{' takes 15 bits on its own
+        let headers = [
+            Header {name: "aaa".into(), value: "{".to_string()},
+        ];
+        let encoded = encoder.encode(&headers);
+
+        assert_eq!(encoded, vec![64, 130, 24, 199, 1, 123]);
+    }
+
+    #[test]
+    fn test_builder_signals_header_table_size() {
+        let mut encoder = Encoder::builder()
+            .max_header_table_size(4096)
+            .header_table_size(256)
+            .build();
+        assert_eq!(encoder.header_table_size(), 256);
+
+        let headers = [
+            Header{name: ":method".into(), value: "GET".to_string()}
+        ];
+        assert_eq!(encoder.encode(&headers), vec![63, 225, 1, 130]);
+        assert_eq!(encoder.encode(&headers), vec![130]);
+    }
+
+    #[test]
+    fn test_header_table_size_bounded_by_max() {
+        let mut encoder = Encoder::new();
+        encoder.set_header_table_size(8192);
+        assert_eq!(encoder.header_table_size(), 4096);
+
+        encoder.set_max_header_table_size(1024);
+        assert_eq!(encoder.header_table_size(), 1024);
+    }
+
+    #[test]
+    fn test_signals_smallest_and_final_size() {
+        let mut encoder = Encoder::new();
+        let headers = [Header::new(":method", "GET")];
+        for size in [100, 0, 200, 50] {
+            encoder.set_header_table_size(size);
+        }
+        assert_eq!(encoder.encode(&headers), vec![32, 63, 19, 130]);
+
+        encoder.set_header_table_size(300);
+        encoder.set_header_table_size(100);
+        assert_eq!(encoder.encode(&headers), vec![63, 69, 130]);
+        assert_eq!(encoder.stats().table_size_updates, 3);
+    }
+
+    #[test]
+    fn test_encode_decode_huffman_round_trip() {
+        let mut encoder = Encoder::builder().huffman(HuffmanPolicy::Always).build();
+        let mut decoder = Decoder::new();
+        let headers = [
+            Header {name: "user-agent".into(), value: "Mozilla/5.0 (X11; Linux x86_64)".to_string()},
+            Header {name: "x-binary".into(), value: "\u{7f}~|}{\u{1}".to_string()},
+        ];
+
+        let decoded = decoder.decode(&encoder.encode(&headers)).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].value, headers[0].value);
+        assert_eq!(decoded[1].value, headers[1].value);
+    }
 }
 
 #[cfg(test)]
 mod decoder_tests {
-    use crate::hpack::decoder::Decoder;
-    use crate::hpack::header::Header;
+    use crate::hpack::decoder::{Decoder, DecoderError, DecoderPolicy};
+    use crate::hpack::encoder::Encoder;
+    use crate::hpack::header::{EncodableHeader, Header};
 
     #[test]
     fn test_decode_10_with_5_prefix() {
@@ -142,7 +275,7 @@ mod decoder_tests {
     #[test]
     fn test_default_header_table_size() {
         let decoder = Decoder::new();
-        assert_eq!(decoder.header_table.max_size, 4096);
+        assert_eq!(decoder.header_table_size(), 4096);
     }
 
     #[test]
@@ -186,6 +319,37 @@ mod decoder_tests {
         assert_eq!(headers[0].value, "secret");
     }
 
+    #[test]
+    fn test_decode_never_indexed_is_sensitive() {
+        let mut decoder = Decoder::new();
+        let path = "/sample/path";
+        let mut encoded: Vec<u8> = vec![20, path.len().try_into().unwrap()];
+        encoded.append(&mut path.as_bytes().to_vec());
+        encoded.append(&mut vec![4, 1, 47]);
+
+        let headers = decoder.decode_headers(&encoded).unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers[0].name, ":path");
+        assert_eq!(headers[0].value, path);
+        assert!(headers[0].is_sensitive);
+        assert!(!headers[1].is_sensitive);
+        assert!(decoder.header_table().search_with_name_and_value(":path", path).is_none());
+    }
+
+    #[test]
+    fn test_encode_decode_sensitive_round_trip() {
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+        let headers = [
+            EncodableHeader {name: "authorization".into(), value: "secret".to_string(), is_sensitive: true},
+            EncodableHeader {name: "x-token".into(), value: "secret".to_string(), is_sensitive: true},
+            EncodableHeader {name: "accept".into(), value: "*/*".to_string(), is_sensitive: false},
+        ];
+
+        let decoded = decoder.decode_headers(&encoder.encode_headers(&headers)).unwrap();
+        assert_eq!(decoded, headers);
+    }
+
     #[test]
     fn test_decode_literal_with_indexing() {
         let mut decoder = Decoder::new();
@@ -197,7 +361,7 @@ mod decoder_tests {
         assert_eq!(headers.len(), 1);
         assert_eq!(headers[0].name, ":path");
         assert_eq!(headers[0].value, path);
-        assert_eq!(decoder.header_table.search_with_name_and_value(":path", path).unwrap(), 62);
+        assert_eq!(decoder.header_table().search_with_name_and_value(":path", path).unwrap(), 62);
     }
 
     #[test]
@@ -205,10 +369,10 @@ mod decoder_tests {
         let mut first_bytes: Vec<u8> = vec![130, 134, 132, 1, 15];
         first_bytes.append(&mut "www.example.com".as_bytes().to_vec());
         let first_headers: Vec<Header> = vec![
-            Header {name: ":method".to_string(), value: "GET".to_string()},
-            Header {name: ":scheme".to_string(), value: "http".to_string()},
-            Header {name: ":path".to_string(), value: "/".to_string()},
-            Header {name: ":authority".to_string(), value: "www.example.com".to_string()},
+            Header {name: ":method".into(), value: "GET".to_string()},
+            Header {name: ":scheme".into(), value: "http".to_string()},
+            Header {name: ":path".into(), value: "/".to_string()},
+            Header {name: ":authority".into(), value: "www.example.com".to_string()},
         ];
 
         let mut second_bytes: Vec<u8> = vec![130, 134, 132, 1, 15];
@@ -216,11 +380,11 @@ mod decoder_tests {
         second_bytes.append(&mut vec![15, 9, 8]);
         second_bytes.append(&mut "no-cache".as_bytes().to_vec());
         let second_headers: Vec<Header> = vec![
-            Header {name: ":method".to_string(), value: "GET".to_string()},
-            Header {name: ":scheme".to_string(), value: "http".to_string()},
-            Header {name: ":path".to_string(), value: "/".to_string()},
-            Header {name: ":authority".to_string(), value: "www.example.com".to_string()},
-            Header {name: "cache-control".to_string(), value: "no-cache".to_string()},
+            Header {name: ":method".into(), value: "GET".to_string()},
+            Header {name: ":scheme".into(), value: "http".to_string()},
+            Header {name: ":path".into(), value: "/".to_string()},
+            Header {name: ":authority".into(), value: "www.example.com".to_string()},
+            Header {name: "cache-control".into(), value: "no-cache".to_string()},
         ];
 
         let mut third_bytes: Vec<u8> = vec![130, 135, 133, 1, 15];
@@ -230,11 +394,11 @@ mod decoder_tests {
         third_bytes.append(&mut vec![12]);
         third_bytes.append(&mut "custom-value".as_bytes().to_vec());
         let third_headers: Vec<Header> = vec![
-            Header {name: ":method".to_string(), value: "GET".to_string()},
-            Header {name: ":scheme".to_string(), value: "http".to_string()},
-            Header {name: ":path".to_string(), value: "/index.html".to_string()},
-            Header {name: ":authority".to_string(), value: "www.example.com".to_string()},
-            Header {name: "custom-key".to_string(), value: "custom-value".to_string()},
+            Header {name: ":method".into(), value: "GET".to_string()},
+            Header {name: ":scheme".into(), value: "https".to_string()},
+            Header {name: ":path".into(), value: "/index.html".to_string()},
+            Header {name: ":authority".into(), value: "www.example.com".to_string()},
+            Header {name: "custom-key".into(), value: "custom-value".to_string()},
         ];
 
         let mut decoder = Decoder::new();
@@ -252,7 +416,9 @@ mod decoder_tests {
         }
 
         compare(&first_headers, &first_decoded);
-        assert_eq!(decoder.header_table.search_with_name_and_value("custom-key", "custom-value").unwrap(), 62);
+        compare(&second_headers, &second_decoded);
+        compare(&third_headers, &third_decoded);
+        assert_eq!(decoder.header_table().search_with_name_and_value("custom-key", "custom-value").unwrap(), 62);
     }
 
     #[test]
@@ -262,18 +428,1430 @@ mod decoder_tests {
 
         let headers = decoder.decode(&encoded).unwrap();
         assert_eq!(headers.len(), 0);
-        assert_eq!(decoder.header_table.max_size, 30);
+        assert_eq!(decoder.header_table_size(), 30);
     }
 
     #[test]
-    fn test_decode_no_huffman_support() {
+    fn test_decode_huffman() {
         let mut decoder = Decoder::new();
         let encoded: Vec<u8> = vec![
             130, 134, 132, 1, 140, 241, 227, 194, 229, 242, 58, 107, 160,
             171, 144, 244, 255
         ];
 
-        let headers = decoder.decode(&encoded);
-        assert!(headers.is_err());
+        let headers = decoder.decode(&encoded).unwrap();
+        assert_eq!(headers.len(), 4);
+        assert_eq!(headers[3].name, ":authority");
+        assert_eq!(headers[3].value, "www.example.com");
+    }
+
+    #[test]
+    fn test_decode_invalid_huffman_padding() {
+        let mut decoder = Decoder::new();
+        // 'a' is 00011, padded with zeros instead of ones
+        let encoded: Vec<u8> = vec![4, 129, 0b0001_1000];
+
+        let result = decoder.decode(&encoded);
+        assert_eq!(result.unwrap_err(), DecoderError::InvalidHuffman);
+    }
+
+    #[test]
+    fn test_builder_header_table_sizes() {
+        let decoder = Decoder::builder()
+            .max_header_table_size(8192)
+            .max_header_list_size(16384)
+            .build();
+        assert_eq!(decoder.header_table_size(), 8192);
+        assert_eq!(decoder.max_header_table_size(), 8192);
+        assert_eq!(decoder.max_header_list_size(), 16384);
+
+        let decoder = Decoder::builder().header_table_size(256).build();
+        assert_eq!(decoder.header_table_size(), 256);
+        assert_eq!(decoder.max_header_table_size(), 4096);
+    }
+
+    #[test]
+    fn test_decode_table_size_update_above_max() {
+        let mut decoder = Decoder::builder().max_header_table_size(100).build();
+
+        // 100 is accepted, 200 is not
+        assert!(decoder.decode(&[63, 69]).is_ok());
+        let result = decoder.decode(&[63, 169, 1]);
+        assert_eq!(result.unwrap_err(), DecoderError::InvalidTableSizeUpdate);
+    }
+
+    #[test]
+    fn test_lowered_max_requires_size_update() {
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+        decoder.decode(&encoder.encode(&[Header::new("custom-key", "custom-value")])).unwrap();
+
+        // the table shrinks at once, entries that no longer fit are gone
+        decoder.set_max_header_table_size(40);
+        assert_eq!(decoder.header_table_size(), 40);
+        assert!(decoder.header_table().is_empty());
+
+        assert_eq!(decoder.decode(&[130]).unwrap_err(), DecoderError::MissingTableSizeUpdate);
+        assert_eq!(decoder.decode(&[]).unwrap_err(), DecoderError::MissingTableSizeUpdate);
+        assert_eq!(decoder.decode(&[63, 10]).unwrap_err(), DecoderError::InvalidTableSizeUpdate);
+        assert_eq!(decoder.decode(&[63, 9, 130]).unwrap().len(), 1);
+        assert_eq!(decoder.header_table_size(), 40);
+
+        // only the next block must carry it
+        assert_eq!(decoder.decode(&[130]).unwrap().len(), 1);
+        decoder.set_max_header_table_size(4096);
+        assert_eq!(decoder.decode(&[130]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_size_update_after_field() {
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.decode(&[130, 32]).unwrap_err(), DecoderError::UnexpectedTableSizeUpdate);
+    }
+
+    #[test]
+    fn test_too_many_size_updates() {
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.decode(&[32, 63, 225, 31, 130]).unwrap().len(), 1);
+        assert_eq!(decoder.decode(&[32, 32, 32, 130]).unwrap_err(), DecoderError::UnexpectedTableSizeUpdate);
+    }
+
+    #[test]
+    fn test_default_max_header_list_size() {
+        let decoder = Decoder::new();
+        assert_eq!(decoder.max_header_list_size(), usize::MAX);
+    }
+
+    #[test]
+    fn test_decode_within_max_header_list_size() {
+        let mut decoder = Decoder::new();
+        // :method GET is 7 + 3 + 32 = 42 octets
+        decoder.set_max_header_list_size(42);
+
+        let headers = decoder.decode(&[130]).unwrap();
+        assert_eq!(headers.len(), 1);
+    }
+
+    #[test]
+    fn test_decode_exceeds_max_header_list_size() {
+        let mut decoder = Decoder::new();
+        decoder.set_max_header_list_size(83);
+
+        let result = decoder.decode(&[130, 130]);
+        assert_eq!(result.unwrap_err(), DecoderError::HeaderListTooLarge);
+    }
+
+    #[test]
+    fn test_decode_exceeding_max_header_list_size_keeps_table_in_sync() {
+        let mut decoder = Decoder::new();
+        decoder.set_max_header_list_size(50);
+        let mut encoded: Vec<u8> = vec![130, 64, 10];
+        encoded.append(&mut "custom-key".as_bytes().to_vec());
+        encoded.push(12);
+        encoded.append(&mut "custom-value".as_bytes().to_vec());
+
+        let result = decoder.decode(&encoded);
+        assert_eq!(result.unwrap_err(), DecoderError::HeaderListTooLarge);
+        assert_eq!(decoder.header_table().search_with_name_and_value("custom-key", "custom-value").unwrap(), 62);
+    }
+
+    #[test]
+    fn test_decode_invalid_index() {
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.decode(&[128]).unwrap_err(), DecoderError::InvalidIndex);
+        assert_eq!(decoder.decode(&[190]).unwrap_err(), DecoderError::InvalidIndex);
+        assert_eq!(decoder.decode(&[127, 0, 1, 97]).unwrap_err(), DecoderError::InvalidIndex);
+    }
+
+    #[test]
+    fn test_decode_truncated_int() {
+        let decoder = Decoder::new();
+        let result = decoder.decode_int(&[31, 154], 5);
+        assert_eq!(result.unwrap_err(), DecoderError::Truncated);
+    }
+
+    #[test]
+    fn test_decode_int_overflow() {
+        let decoder = Decoder::new();
+        let encoded = [31, 255, 255, 255, 255, 255, 255, 255, 255, 255, 1];
+        let result = decoder.decode_int(&encoded, 5);
+        assert_eq!(result.unwrap_err(), DecoderError::IntegerOverflow);
+    }
+
+    #[test]
+    fn test_decode_truncated_string() {
+        let mut decoder = Decoder::new();
+        // claims a 127 + 1000 octet value but carries only 3
+        let encoded: Vec<u8> = vec![4, 127, 232, 7, 97, 98, 99];
+
+        let result = decoder.decode(&encoded);
+        assert_eq!(result.unwrap_err(), DecoderError::Truncated);
+    }
+
+    #[test]
+    fn test_decode_string_too_long() {
+        let mut decoder = Decoder::new();
+        decoder.set_policy(DecoderPolicy {max_string_length: 8, ..DecoderPolicy::default()});
+        let path = "/sample/path";
+        let mut encoded: Vec<u8> = vec![4, path.len().try_into().unwrap()];
+        encoded.append(&mut path.as_bytes().to_vec());
+
+        let result = decoder.decode(&encoded);
+        assert_eq!(result.unwrap_err(), DecoderError::StringTooLong);
+    }
+
+    #[test]
+    fn test_decode_too_many_fields() {
+        let mut decoder = Decoder::new();
+        decoder.set_policy(DecoderPolicy {max_field_count: 2, ..DecoderPolicy::default()});
+
+        assert_eq!(decoder.decode(&[130, 134]).unwrap().len(), 2);
+        assert_eq!(decoder.decode(&[130, 134, 132]).unwrap_err(), DecoderError::TooManyFields);
+    }
+
+    #[test]
+    fn test_decode_expansion_ratio_exceeded() {
+        let mut decoder = Decoder::new();
+        let value = "a".repeat(100);
+        let mut literal: Vec<u8> = vec![64, 3];
+        literal.append(&mut "big".as_bytes().to_vec());
+        literal.push(100);
+        literal.append(&mut value.as_bytes().to_vec());
+        decoder.decode(&literal).unwrap();
+
+        decoder.set_policy(DecoderPolicy {
+            max_expansion_ratio: 10,
+            expansion_ratio_threshold: 64,
+            ..DecoderPolicy::default()
+        });
+
+        // small blocks stay below the threshold, so their ratio is not enforced
+        assert_eq!(decoder.decode(&[130]).unwrap().len(), 1);
+        let result = decoder.decode(&[190, 190, 190]);
+        assert_eq!(result.unwrap_err(), DecoderError::ExpansionRatioExceeded);
+    }
+
+    #[test]
+    fn test_default_policy_limits() {
+        let encoder = Encoder::new();
+        let literal = |with_indexing: bool, length: usize| {
+            let mut encoded: Vec<u8> = vec![if with_indexing { 64 } else { 0 }, 1, b'a'];
+            encoded.append(&mut encoder.encode_int(length as u64, 7));
+            encoded.append(&mut "a".repeat(length).into_bytes());
+            encoded
+        };
+
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.decode(&literal(false, 65_537)).unwrap_err(), DecoderError::StringTooLong);
+
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.decode(&[130; 2_048]).unwrap().len(), 2_048);
+        assert_eq!(decoder.decode(&[130; 2_049]).unwrap_err(), DecoderError::TooManyFields);
+
+        // a 4000 octet entry referenced over and over
+        let mut decoder = Decoder::new();
+        decoder.decode(&literal(true, 3_966)).unwrap();
+        assert_eq!(decoder.decode(&[190; 16]).unwrap().len(), 16);
+        assert_eq!(decoder.decode(&[190; 17]).unwrap_err(), DecoderError::ExpansionRatioExceeded);
+    }
+}
+
+#[cfg(test)]
+mod validation_tests {
+    use crate::hpack::decoder::{Decoder, DecoderError};
+    use crate::hpack::encoder::Encoder;
+    use crate::hpack::header::Header;
+    use crate::hpack::validation::{validate, Validation, ValidationError};
+
+    fn headers(fields: &[(&str, &str)]) -> Vec<Header> {
+        fields.iter()
+            .map(|(name, value)| Header::new(*name, *value))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_request() {
+        let request = headers(&[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":path", "/"),
+            ("te", "trailers"),
+            ("accept", "*/*"),
+        ]);
+        assert!(validate(&request, Validation::Strict).is_ok());
+    }
+
+    #[test]
+    fn test_disabled_accepts_anything() {
+        let request = headers(&[("Connection", "close\r\n")]);
+        assert!(validate(&request, Validation::Disabled).is_ok());
+    }
+
+    #[test]
+    fn test_uppercase_name() {
+        let request = headers(&[("Accept", "*/*")]);
+        let result = validate(&request, Validation::Fields);
+        assert_eq!(result.unwrap_err(), ValidationError::UppercaseName("Accept".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_name() {
+        let request = headers(&[("x header", "1")]);
+        let result = validate(&request, Validation::Fields);
+        assert_eq!(result.unwrap_err(), ValidationError::InvalidName("x header".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        for value in ["a\rb", "a\nb", "a\0b"] {
+            let request = headers(&[("x-header", value)]);
+            let result = validate(&request, Validation::Fields);
+            assert_eq!(result.unwrap_err(), ValidationError::InvalidValue("x-header".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_fields_skips_list_rules() {
+        let request = headers(&[("connection", "close"), (":method", "GET")]);
+        assert!(validate(&request, Validation::Fields).is_ok());
+    }
+
+    #[test]
+    fn test_pseudo_header_after_regular() {
+        let request = headers(&[(":method", "GET"), ("accept", "*/*"), (":path", "/")]);
+        let result = validate(&request, Validation::Strict);
+        assert_eq!(result.unwrap_err(), ValidationError::PseudoHeaderAfterRegular(":path".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_and_duplicate_pseudo_headers() {
+        let request = headers(&[(":verb", "GET")]);
+        let result = validate(&request, Validation::Strict);
+        assert_eq!(result.unwrap_err(), ValidationError::UnknownPseudoHeader(":verb".to_string()));
+
+        let request = headers(&[(":method", "GET"), (":method", "POST")]);
+        let result = validate(&request, Validation::Strict);
+        assert_eq!(result.unwrap_err(), ValidationError::DuplicatePseudoHeader(":method".to_string()));
+    }
+
+    #[test]
+    fn test_mixed_pseudo_headers() {
+        let request = headers(&[(":method", "GET"), (":status", "200")]);
+        let result = validate(&request, Validation::Strict);
+        assert_eq!(result.unwrap_err(), ValidationError::MixedPseudoHeaders(":status".to_string()));
+    }
+
+    #[test]
+    fn test_connection_specific_headers() {
+        for name in ["connection", "keep-alive", "transfer-encoding"] {
+            let request = headers(&[(name, "x")]);
+            let result = validate(&request, Validation::Strict);
+            assert_eq!(result.unwrap_err(), ValidationError::ConnectionSpecific(name.to_string()));
+        }
+
+        let request = headers(&[("te", "gzip")]);
+        let result = validate(&request, Validation::Strict);
+        assert_eq!(result.unwrap_err(), ValidationError::InvalidTe("gzip".to_string()));
+    }
+
+    #[test]
+    fn test_decoder_validation() {
+        let mut decoder = Decoder::builder().validation(Validation::Strict).build();
+        // cache-control followed by :method
+        let encoded: Vec<u8> = vec![15, 9, 8, 110, 111, 45, 99, 97, 99, 104, 101, 130];
+
+        let result = decoder.decode(&encoded);
+        let expected = ValidationError::PseudoHeaderAfterRegular(":method".to_string());
+        assert_eq!(result.unwrap_err(), DecoderError::Malformed(expected));
+    }
+
+    #[test]
+    fn test_encoder_validation() {
+        let mut encoder = Encoder::builder().validation(Validation::Strict).build();
+        let request = headers(&[(":method", "GET"), ("keep-alive", "timeout=5")]);
+
+        let result = encoder.try_encode(&request);
+        assert_eq!(result.unwrap_err(), ValidationError::ConnectionSpecific("keep-alive".to_string()));
+        assert!(encoder.header_table().search_with_name("keep-alive").is_none());
+    }
+}
+
+#[cfg(test)]
+mod head_tests {
+    use crate::hpack::decoder::{Decoder, DecoderError};
+    use crate::hpack::encoder::Encoder;
+    use crate::hpack::head::{HeadError, RequestHead, ResponseHead};
+    use crate::hpack::header::Header;
+    use crate::hpack::validation::ValidationError;
+
+    fn header(name: &str, value: &str) -> Header {
+        Header::new(name, value)
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        let mut request = RequestHead::new("GET", "https", "www.example.com", "/index.html");
+        request.headers.push(header("accept", "*/*"));
+
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+        let encoded = request.encode(&mut encoder).unwrap();
+        assert_eq!(RequestHead::decode(&mut decoder, &encoded).unwrap(), request);
+    }
+
+    #[test]
+    fn test_request_pseudo_headers_first() {
+        let mut request = RequestHead::new("GET", "https", "www.example.com", "/");
+        request.headers.push(header("accept", "*/*"));
+
+        let headers = request.to_headers().unwrap();
+        let names: Vec<&str> = headers.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, vec![":method", ":scheme", ":authority", ":path", "accept"]);
+    }
+
+    #[test]
+    fn test_request_missing_pseudo_headers() {
+        let result = RequestHead::from_headers(vec![header(":scheme", "https"), header(":path", "/")]);
+        assert_eq!(result.unwrap_err(), HeadError::MissingPseudoHeader(":method"));
+
+        let result = RequestHead::from_headers(vec![header(":method", "GET"), header(":scheme", "https")]);
+        assert_eq!(result.unwrap_err(), HeadError::MissingPseudoHeader(":path"));
+
+        let mut request = RequestHead::new("GET", "https", "www.example.com", "");
+        assert_eq!(request.to_headers().unwrap_err(), HeadError::MissingPseudoHeader(":path"));
+        request.path = Some("/".to_string());
+        request.scheme = None;
+        assert_eq!(request.to_headers().unwrap_err(), HeadError::MissingPseudoHeader(":scheme"));
+    }
+
+    #[test]
+    fn test_request_pseudo_header_order() {
+        let headers = vec![header(":method", "GET"), header("accept", "*/*"), header(":path", "/")];
+        let result = RequestHead::from_headers(headers);
+        let expected = ValidationError::PseudoHeaderAfterRegular(":path".to_string());
+        assert_eq!(result.unwrap_err(), HeadError::Malformed(expected));
+    }
+
+    #[test]
+    fn test_connect() {
+        let request = RequestHead::connect("proxy.example.com:443");
+        let headers = request.to_headers().unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(RequestHead::from_headers(headers).unwrap(), request);
+
+        let mut request = RequestHead::connect("proxy.example.com:443");
+        request.path = Some("/".to_string());
+        assert_eq!(request.to_headers().unwrap_err(), HeadError::UnexpectedPseudoHeader(":path".to_string()));
+
+        let request = RequestHead::connect("");
+        assert_eq!(request.to_headers().unwrap_err(), HeadError::MissingPseudoHeader(":authority"));
+    }
+
+    #[test]
+    fn test_extended_connect() {
+        let mut request = RequestHead::new("CONNECT", "https", "www.example.com", "/chat");
+        request.protocol = Some("websocket".to_string());
+
+        let headers = request.to_headers().unwrap();
+        assert_eq!(headers[4].name, ":protocol");
+        assert_eq!(RequestHead::from_headers(headers).unwrap(), request);
+
+        request.method = "GET".to_string();
+        let expected = HeadError::UnexpectedPseudoHeader(":protocol".to_string());
+        assert_eq!(request.to_headers().unwrap_err(), expected);
+    }
+
+    #[test]
+    fn test_response_round_trip() {
+        let mut response = ResponseHead::new(404);
+        response.headers.push(header("content-type", "text/plain"));
+
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+        let encoded = response.encode(&mut encoder).unwrap();
+        assert_eq!(encoded[0], 141);
+        assert_eq!(ResponseHead::decode(&mut decoder, &encoded).unwrap(), response);
+    }
+
+    #[test]
+    fn test_response_invalid() {
+        let result = ResponseHead::from_headers(vec![header("server", "x")]);
+        assert_eq!(result.unwrap_err(), HeadError::MissingPseudoHeader(":status"));
+
+        let result = ResponseHead::from_headers(vec![header(":status", "20")]);
+        assert_eq!(result.unwrap_err(), HeadError::InvalidStatus("20".to_string()));
+
+        let result = ResponseHead::from_headers(vec![header(":path", "/")]);
+        assert_eq!(result.unwrap_err(), HeadError::UnexpectedPseudoHeader(":path".to_string()));
+
+        assert_eq!(ResponseHead::new(42).to_headers().unwrap_err(), HeadError::InvalidStatus("42".to_string()));
+    }
+
+    #[test]
+    fn test_decode_error() {
+        let mut decoder = Decoder::new();
+        let result = RequestHead::decode(&mut decoder, &[4]);
+        assert_eq!(result.unwrap_err(), HeadError::Decoder(DecoderError::EmptyData));
+    }
+}
+
+#[cfg(all(test, feature = "http"))]
+mod interop_tests {
+    use http::header::HeaderValue;
+    use http::{Method, Request, Response, StatusCode};
+    use crate::hpack::decoder::Decoder;
+    use crate::hpack::encoder::Encoder;
+    use crate::hpack::head::HeadError;
+    use crate::hpack::interop::{self, InteropError, Protocol};
+
+    #[test]
+    fn test_request_round_trip() {
+        let mut token = HeaderValue::from_static("Bearer secret");
+        token.set_sensitive(true);
+        let (parts, _) = Request::builder()
+            .method(Method::POST)
+            .uri("https://www.example.com/upload?id=1")
+            .header("content-type", "text/plain")
+            .header("authorization", token)
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let headers = interop::from_request_parts(&parts).unwrap();
+        assert_eq!(headers[0].name, ":method");
+        assert_eq!(headers[3].value, "/upload?id=1");
+        assert!(headers[5].is_sensitive);
+
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+        let decoded = decoder.decode_headers(&encoder.encode_headers(&headers)).unwrap();
+        let request = interop::to_request_parts(decoded).unwrap();
+
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.uri, parts.uri);
+        assert_eq!(request.headers, parts.headers);
+        assert!(request.headers["authorization"].is_sensitive());
+        assert!(!request.headers["content-type"].is_sensitive());
+    }
+
+    #[test]
+    fn test_extended_connect() {
+        let (parts, _) = Request::builder()
+            .method(Method::CONNECT)
+            .uri("https://www.example.com/chat")
+            .extension(Protocol("websocket".to_string()))
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let headers = interop::from_request_parts(&parts).unwrap();
+        assert_eq!(headers[4].name, ":protocol");
+
+        let request = interop::to_request_parts(headers).unwrap();
+        assert_eq!(request.extensions.get::<Protocol>().unwrap().0, "websocket");
+    }
+
+    #[test]
+    fn test_response_round_trip() {
+        let (parts, _) = Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .header("server", "http2")
+            .header("set-cookie", "a=1")
+            .header("set-cookie", "b=2")
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let headers = interop::from_response_parts(&parts).unwrap();
+        assert_eq!(headers.len(), 4);
+
+        let response = interop::to_response_parts(headers).unwrap();
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+        assert_eq!(response.headers, parts.headers);
+    }
+
+    #[test]
+    fn test_connect_without_authority() {
+        let (parts, _) = Request::builder()
+            .method(Method::CONNECT)
+            .uri("/")
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let result = interop::from_request_parts(&parts);
+        assert!(matches!(result, Err(InteropError::Head(HeadError::MissingPseudoHeader(":authority")))));
+    }
+
+    #[test]
+    fn test_missing_pseudo_header() {
+        let headers = interop::from_header_map(&http::HeaderMap::new()).unwrap();
+        let result = interop::to_response_parts(headers);
+        assert!(matches!(result, Err(InteropError::Head(HeadError::MissingPseudoHeader(":status")))));
+    }
+}
+
+#[cfg(test)]
+mod header_table_tests {
+    use crate::hpack::decoder::{Decoder, DecoderError};
+    use crate::hpack::encoder::Encoder;
+    use crate::hpack::header::{Header, HeaderName};
+    use std::sync::{Arc, Mutex};
+    use crate::hpack::header_table::{HeaderTable, SnapshotError, TableEntry, TableObserver, TableSnapshot};
+
+    #[test]
+    fn test_static_header_name() {
+        let name = HeaderName::from(":status");
+        assert_eq!(name.static_index(), Some(4));
+        assert_eq!(name, ":status");
+        assert_eq!(name, HeaderName::from(":status".to_string()));
+    }
+
+    #[test]
+    fn test_custom_header_name() {
+        let name = HeaderName::from("custom-key".to_string());
+        assert_eq!(name.static_index(), None);
+        assert_eq!(name.as_str(), "custom-key");
+        assert_ne!(name, HeaderName::from("content-type"));
+    }
+
+    #[test]
+    fn test_search_static() {
+        let table = HeaderTable::new_default();
+        assert_eq!(table.search_with_name(":status"), Some(8));
+        assert_eq!(table.search_with_name_and_value(":status", "304"), Some(11));
+        assert_eq!(table.search_with_name_and_value(":status", "418"), None);
+        assert_eq!(table.search_with_name("www-authenticate"), Some(61));
+        assert_eq!(table.search_with_name("custom-key"), None);
+    }
+
+    #[test]
+    fn test_search_dynamic() {
+        let mut table = HeaderTable::new_default();
+        table.add(":status", "418");
+        table.add("custom-key", "custom-value");
+
+        assert_eq!(table.search_with_name_and_value(":status", "418"), Some(63));
+        assert_eq!(table.search_with_name("custom-key"), Some(62));
+        assert_eq!(table.search_with_name_and_value("custom-key", "custom-value"), Some(62));
+        assert_eq!(table.search_with_name_and_value("custom-key", "other"), None);
+    }
+
+    #[test]
+    fn test_search_header_name() {
+        let mut table = HeaderTable::new_default();
+        table.add_header(Header::new(":status", "418"));
+        table.add_header(Header::new("custom-key", "custom-value"));
+
+        let status = HeaderName::from(":status");
+        let custom = HeaderName::from("custom-key");
+        assert_eq!(table.search_with_header_name(&status), Some(8));
+        assert_eq!(table.search_with_header_name_and_value(&status, "304"), Some(11));
+        assert_eq!(table.search_with_header_name_and_value(&status, "418"), Some(63));
+        assert_eq!(table.search_with_header_name(&custom), Some(62));
+        assert_eq!(table.search_with_header_name_and_value(&custom, "other"), None);
+    }
+
+    #[test]
+    fn test_add_evicts_oldest() {
+        // each entry is 1 + 1 + 32 = 34 octets
+        let mut table = HeaderTable::new(100);
+        table.add("a", "1");
+        table.add("b", "2");
+        table.add("c", "3");
+
+        let snapshot = table.snapshot();
+        assert_eq!(snapshot.size, 68);
+        assert_eq!(snapshot.entries, vec![Header::new("c", "3"), Header::new("b", "2")]);
+        assert_eq!(*table.get(62).unwrap(), Header::new("c", "3"));
+    }
+
+    #[test]
+    fn test_add_larger_than_table() {
+        let mut table = HeaderTable::new(40);
+        table.add("a", "1");
+        table.add("bigger", "value");
+
+        let snapshot = table.snapshot();
+        assert_eq!(snapshot.size, 0);
+        assert!(snapshot.entries.is_empty());
+    }
+
+    #[test]
+    fn test_set_max_size_evicts() {
+        let mut table = HeaderTable::new_default();
+        table.add("a", "1");
+        table.add("b", "2");
+
+        table.set_max_size(34);
+        assert_eq!(table.snapshot().entries, vec![Header::new("b", "2")]);
+
+        table.set_max_size(0);
+        assert_eq!(table.snapshot().size, 0);
+    }
+
+    #[test]
+    fn test_introspection() {
+        let mut table = HeaderTable::new(100);
+        assert!(table.is_empty());
+
+        table.add("a", "1");
+        table.add("b", "2");
+        table.add("c", "3");
+        assert_eq!(table.size(), 68);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.insert_count(), 3);
+        assert_eq!(table.eviction_count(), 1);
+
+        let entries: Vec<TableEntry> = table.entries().collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].index, 62);
+        assert_eq!(entries[0].absolute_index, 2);
+        assert_eq!(*entries[0].header, Header::new("c", "3"));
+        assert_eq!(entries[1].index, 63);
+        assert_eq!(entries[1].absolute_index, 1);
+    }
+
+    #[test]
+    fn test_get() {
+        let mut table = HeaderTable::new_default();
+        table.add("custom-key", "custom-value");
+
+        assert_eq!(table.get(0), None);
+        assert_eq!(table.get(2).as_deref(), Some(&Header::new(":method", "GET")));
+        assert_eq!(table.get(62).as_deref(), Some(&Header::new("custom-key", "custom-value")));
+        assert_eq!(table.get(63), None);
+
+        assert_eq!(table.get_name(0), None);
+        assert_eq!(table.get_name(2), Some(HeaderName::from(":method")));
+        assert_eq!(table.get_name(62), Some(HeaderName::from("custom-key")));
+        assert_eq!(table.get_name(63), None);
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl TableObserver for Recorder {
+        fn on_insert(&mut self, entry: TableEntry<'_>) {
+            let event = format!("insert {} {} {}", entry.index, entry.absolute_index, entry.header.name);
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn on_evict(&mut self, entry: TableEntry<'_>) {
+            let event = format!("evict {} {} {}", entry.index, entry.absolute_index, entry.header.name);
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn on_resize(&mut self, old_max_size: usize, new_max_size: usize) {
+            self.events.lock().unwrap().push(format!("resize {} {}", old_max_size, new_max_size));
+        }
+    }
+
+    #[test]
+    fn test_observer() {
+        let recorder = Recorder::default();
+        let events = recorder.events.clone();
+        let mut table = HeaderTable::new(100);
+        table.set_observer(Box::new(recorder));
+
+        table.add("a", "1");
+        table.add("b", "2");
+        table.add("c", "3");
+        table.set_max_size(40);
+        table.add("too-large", "value");
+
+        assert_eq!(*events.lock().unwrap(), vec![
+            "insert 62 0 a",
+            "insert 62 1 b",
+            "evict 63 0 a",
+            "insert 62 2 c",
+            "resize 100 40",
+            "evict 63 1 b",
+            "evict 62 2 c",
+        ]);
+        assert!(table.take_observer().is_some());
+    }
+
+    #[test]
+    fn test_codec_observer() {
+        let recorder = Recorder::default();
+        let events = recorder.events.clone();
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+        decoder.set_observer(Box::new(recorder));
+
+        decoder.decode(&encoder.encode(&[Header::new("custom-key", "custom-value")])).unwrap();
+        assert_eq!(*events.lock().unwrap(), vec!["insert 62 0 custom-key"]);
+
+        // kept across a restore
+        decoder.restore(&decoder.snapshot()).unwrap();
+        assert!(decoder.take_observer().is_some());
+        assert!(encoder.take_observer().is_none());
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+        let headers = [
+            Header::new(":authority", "www.example.com"),
+            Header::new("custom-key", "custom-value"),
+        ];
+        decoder.decode(&encoder.encode(&headers)).unwrap();
+        assert_eq!(encoder.header_table().snapshot(), decoder.header_table().snapshot());
+
+        // continue the connection on a fresh decoder
+        let mut migrated = Decoder::new();
+        migrated.restore(&decoder.snapshot()).unwrap();
+        let decoded = migrated.decode(&encoder.encode(&headers)).unwrap();
+        assert_eq!(decoded, headers);
+    }
+
+    #[test]
+    fn test_restore_pending_table_size() {
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+        let headers = [Header::new("custom-key", "custom-value")];
+        decoder.decode(&encoder.encode(&headers)).unwrap();
+        encoder.set_header_table_size(1024);
+
+        // the size update moves with the encoder and still reaches the peer
+        let mut migrated = Encoder::new();
+        migrated.restore(&encoder.snapshot()).unwrap();
+        let encoded = migrated.encode(&headers);
+        assert_eq!(encoded, encoder.encode(&headers));
+        assert_eq!(encoded[..3], [0x3f, 0xe1, 0x07]);
+        assert_eq!(decoder.decode(&encoded).unwrap(), headers);
+        assert_eq!(decoder.header_table_size(), 1024);
+
+        let mut restored = Decoder::new();
+        restored.restore(&decoder.snapshot()).unwrap();
+        assert_eq!(restored.snapshot(), decoder.snapshot());
+    }
+
+    #[test]
+    fn test_restore_required_size_update() {
+        let mut decoder = Decoder::new();
+        decoder.set_max_header_table_size(1024);
+        let snapshot = decoder.snapshot();
+        assert!(snapshot.size_update_required);
+
+        // the lowered maximum still has to be confirmed after a restore
+        let mut restored = Decoder::builder().max_header_table_size(1024).build();
+        restored.restore(&snapshot).unwrap();
+        assert_eq!(restored.decode(&[0x82]).unwrap_err(), DecoderError::MissingTableSizeUpdate);
+        assert_eq!(restored.decode(&[0x3f, 0xe1, 0x07, 0x82]).unwrap().len(), 1);
+        assert!(!restored.snapshot().size_update_required);
+    }
+
+    #[test]
+    fn test_restore_exceeds_limit() {
+        let mut encoder = Encoder::builder().max_header_table_size(8192).build();
+        encoder.set_header_table_size(8192);
+        let mut small = Encoder::new();
+        assert_eq!(small.restore(&encoder.snapshot()).err(), Some(SnapshotError::ExceedsLimit));
+
+        let decoder = Decoder::builder().max_header_table_size(8192).header_table_size(8192).build();
+        let mut small = Decoder::new();
+        assert_eq!(small.restore(&decoder.snapshot()).err(), Some(SnapshotError::ExceedsLimit));
+        assert_eq!(small.header_table_size(), 4096);
+    }
+
+    #[test]
+    fn test_restore_invalid_snapshot() {
+        let snapshot = TableSnapshot {entries: vec![Header::new("a", "1")], size: 30, max_size: 4096};
+        assert_eq!(HeaderTable::restore(&snapshot).err(), Some(SnapshotError::SizeMismatch));
+
+        let snapshot = TableSnapshot {entries: vec![Header::new("a", "1")], size: 34, max_size: 32};
+        assert_eq!(HeaderTable::restore(&snapshot).err(), Some(SnapshotError::ExceedsMaxSize));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot_serde() {
+        let mut table = HeaderTable::new_default();
+        table.add(":path", "/index.html");
+        table.add("custom-key", "custom-value");
+
+        let json = serde_json::to_string(&table.snapshot()).unwrap();
+        let snapshot: TableSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot, table.snapshot());
+        assert_eq!(snapshot.entries[1].name.static_index(), Some(2));
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use crate::hpack::decoder::Decoder;
+    use crate::hpack::encoder::{Encoder, HuffmanPolicy};
+    use crate::hpack::header::{EncodableHeader, Header};
+
+    fn request() -> Vec<EncodableHeader> {
+        vec![
+            EncodableHeader::new(":method", "GET", false),
+            EncodableHeader::new(":path", "/index.html", false),
+            EncodableHeader::new("custom-key", "custom-value", false),
+            EncodableHeader::new("authorization", "secret", true),
+        ]
+    }
+
+    #[test]
+    fn test_encoder_stats() {
+        let mut encoder = Encoder::builder().huffman(HuffmanPolicy::Shortest).build();
+        let first = encoder.encode_headers(&request());
+        let second = encoder.encode_headers(&request());
+
+        let stats = encoder.stats();
+        assert_eq!(stats.blocks, 2);
+        assert_eq!(stats.headers, 8);
+        assert_eq!(stats.raw_bytes, 2 * 67);
+        assert_eq!(stats.encoded_bytes, (first.len() + second.len()) as u64);
+        // second block only needs the never indexed authorization as a literal
+        assert_eq!(stats.indexed, 5);
+        assert_eq!(stats.literal_incremental, 1);
+        assert_eq!(stats.literal_never_indexed, 2);
+        assert_eq!(stats.static_hits, 6);
+        assert_eq!(stats.dynamic_hits, 1);
+        assert!(stats.huffman_saved_bytes > 0);
+        assert!(stats.compression_ratio() < 1.0);
+    }
+
+    #[test]
+    fn test_decoder_stats() {
+        let mut encoder = Encoder::builder().huffman(HuffmanPolicy::Always).build();
+        let mut decoder = Decoder::new();
+        for _ in 0..2 {
+            decoder.decode_headers(&encoder.encode_headers(&request())).unwrap();
+        }
+
+        let mut expected = encoder.stats();
+        expected.huffman_strings = decoder.stats().huffman_strings;
+        assert_eq!(decoder.stats(), expected);
+    }
+
+    #[test]
+    fn test_evictions_and_reset() {
+        let mut encoder = Encoder::builder().header_table_size(64).build();
+        let mut decoder = Decoder::new();
+        let headers = [
+            Header::new("a", "1"),
+            Header::new("b", "2"),
+        ];
+        decoder.decode(&encoder.encode(&headers)).unwrap();
+
+        assert_eq!(encoder.stats().table_size_updates, 1);
+        assert_eq!(encoder.stats().evictions, 1);
+        assert_eq!(decoder.stats().evictions, 1);
+
+        encoder.reset_stats();
+        assert_eq!(encoder.stats(), Default::default());
+    }
+}
+
+#[cfg(test)]
+mod disassembler_tests {
+    use crate::hpack::decoder::{Decoder, DecoderError, RepresentationKind};
+    use crate::hpack::disassembler;
+    use crate::hpack::header::Header;
+
+    // RFC 7541 C.4.1, first request with Huffman coding
+    const REQUEST: [u8; 17] = [
+        0x82, 0x86, 0x84, 0x41, 0x8c, 0xf1, 0xe3, 0xc2, 0xe5,
+        0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff,
+    ];
+
+    #[test]
+    fn test_disassemble() {
+        let mut decoder = Decoder::new();
+        let disassembly = disassembler::disassemble(&mut decoder, &REQUEST);
+
+        assert!(disassembly.is_ok());
+        assert_eq!(disassembly.steps.len(), 4);
+        assert_eq!(disassembly.steps[2].offset, 2);
+        assert_eq!(disassembly.steps[2].representation.kind, RepresentationKind::Indexed);
+        assert_eq!(disassembly.steps[2].table.size, 0);
+
+        let step = &disassembly.steps[3];
+        assert_eq!(step.offset, 3);
+        assert_eq!(step.bytes, REQUEST[3..].to_vec());
+        assert_eq!(step.representation.kind, RepresentationKind::IncrementalIndexing);
+        assert_eq!(step.representation.index, 1);
+        assert!(step.representation.value_huffman);
+        assert_eq!(step.representation.header, Some(Header::new(":authority", "www.example.com")));
+        assert_eq!(step.table.entries.len(), 1);
+        assert_eq!(step.table.size, 57);
+
+        let trace = disassembly.to_string();
+        assert!(trace.contains("0003  41 8c f1 e3"));
+        assert!(trace.contains(":authority: www.example.com"));
+        assert!(trace.contains("table: 1 entries, size 57/4096"));
+    }
+
+    #[test]
+    fn test_disassemble_error() {
+        let mut decoder = Decoder::new();
+        let disassembly = disassembler::disassemble(&mut decoder, &REQUEST[0..10]);
+
+        assert_eq!(disassembly.steps.len(), 3);
+        assert_eq!(disassembly.error, Some((3, DecoderError::Truncated)));
+        assert_eq!(disassembly.remaining, REQUEST[3..10].to_vec());
+        assert!(disassembly.to_string().contains("0003  41 8c f1 e3 c2 e5 f2\n      error: "));
+    }
+
+    #[test]
+    fn test_disassemble_keeps_stats() {
+        let mut decoder = Decoder::builder().header_table_size(60).build();
+        decoder.decode(&REQUEST).unwrap();
+        let stats = decoder.stats();
+
+        // the second block evicts the first :authority entry
+        let disassembly = disassembler::disassemble(&mut decoder, &REQUEST);
+        assert!(disassembly.is_ok());
+        assert_eq!(decoder.header_table().eviction_count(), 1);
+        assert_eq!(decoder.stats(), stats);
+    }
+}
+
+#[cfg(test)]
+mod hex_tests {
+    use crate::hpack::hex::{self, HexError};
+
+    #[test]
+    fn test_round_trip() {
+        assert_eq!(hex::encode(&[0x82, 0x0f, 0xff]), "820fff");
+        assert_eq!(hex::decode("820fff").unwrap(), vec![0x82, 0x0f, 0xff]);
+        assert_eq!(hex::decode(" 82 0F\tFF\n").unwrap(), vec![0x82, 0x0f, 0xff]);
+        assert!(hex::decode("").unwrap().is_empty());
+        assert_eq!(hex::encode_spaced(&[0x82, 0x0f, 0xff]), "82 0f ff");
+        assert_eq!(hex::encode_spaced(&[]), "");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(hex::decode("828").unwrap_err(), HexError::OddLength);
+        assert_eq!(hex::decode("8g").unwrap_err(), HexError::InvalidDigit('g'));
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod story_tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::hpack::encoder::{Encoder, HuffmanPolicy};
+    use crate::hpack::header::Header;
+    use crate::hpack::story::{Story, StoryError};
+
+    // every story_*.json under testdata/hpack-test-case, one directory per implementation
+    fn vendored_stories() -> Vec<(PathBuf, Story)> {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/hpack-test-case");
+        let mut paths: Vec<PathBuf> = fs::read_dir(root).unwrap()
+            .flat_map(|x| fs::read_dir(x.unwrap().path()).unwrap())
+            .map(|x| x.unwrap().path())
+            .filter(|x| x.file_name().unwrap().to_str().unwrap().starts_with("story_"))
+            .collect();
+        paths.sort();
+
+        paths.into_iter()
+            .map(|x| {
+                let story = serde_json::from_str(&fs::read_to_string(&x).unwrap());
+                let story = story.unwrap_or_else(|e| panic!("{}: {}", x.display(), e));
+                (x, story)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_vendored_stories() {
+        let stories = vendored_stories();
+        assert!(!stories.is_empty());
+
+        for (path, story) in stories {
+            if let Err(e) = story.verify() {
+                panic!("{}: {}", path.display(), e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_stories() {
+        let policies = [HuffmanPolicy::Never, HuffmanPolicy::Always, HuffmanPolicy::Shortest];
+
+        for (path, story) in vendored_stories() {
+            for (policy, table_size) in policies.iter().flat_map(|x| [(*x, 4096), (*x, 256), (*x, 0)]) {
+                let mut encoder = Encoder::builder()
+                    .max_header_table_size(table_size)
+                    .header_table_size(table_size)
+                    .huffman(policy)
+                    .build();
+                let generated = Story::generate(&mut encoder, &story.description, &story.header_lists());
+
+                let json = serde_json::to_string(&generated).unwrap();
+                assert_eq!(serde_json::from_str::<Story>(&json).unwrap(), generated);
+                if let Err(e) = generated.verify() {
+                    panic!("{} ({:?}, {}): {}", path.display(), policy, table_size, e);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_story_format() {
+        let json = r#"{"cases": [{"seqno": 0, "wire": "82", "headers": [{":method": "GET"}]}]}"#;
+        let story: Story = serde_json::from_str(json).unwrap();
+        assert_eq!(story.cases[0].header_table_size, None);
+        assert_eq!(story.cases[0].wire, vec![0x82]);
+        assert_eq!(story.cases[0].headers, vec![Header::new(":method", "GET")]);
+        assert_eq!(serde_json::to_string(&story).unwrap(), json.replace(' ', ""));
+
+        let json = r#"{"cases": [{"wire": "82", "headers": [{":method": "GET", ":path": "/"}]}]}"#;
+        assert!(serde_json::from_str::<Story>(json).is_err());
+        let json = r#"{"cases": [{"wire": "8", "headers": []}]}"#;
+        assert!(serde_json::from_str::<Story>(json).is_err());
+    }
+
+    #[test]
+    fn test_story_mismatch() {
+        let mut story = vendored_stories().remove(0).1;
+        story.cases[1].headers[0] = Header::new(":method", "POST");
+        assert!(matches!(story.verify(), Err(StoryError::Mismatch(1, _))));
+
+        story.cases[1].wire.truncate(5);
+        assert!(matches!(story.verify(), Err(StoryError::Decoder(1, _))));
+    }
+}
+
+#[cfg(test)]
+mod rfc7541_tests {
+    use crate::hpack::decoder::Decoder;
+    use crate::hpack::encoder::{Encoder, HuffmanPolicy, IndexingStrategy};
+    use crate::hpack::header::{EncodableHeader, Header};
+    use crate::hpack::header_table::HeaderTable;
+    use crate::hpack::hex;
+
+    fn table(header_table: &HeaderTable) -> Vec<(usize, String, String)> {
+        header_table.entries()
+            .map(|x| (x.index, x.header.name.to_string(), x.header.value.clone()))
+            .collect()
+    }
+
+    // dynamic table entries after a block, newest first, and the table size
+    type Table<'a> = (Vec<(usize, &'a str, &'a str)>, usize);
+
+    // (wire, header list, dynamic table after the block, table size)
+    type Step<'a> = (&'a str, Vec<Header>, Vec<(usize, &'a str, &'a str)>, usize);
+
+    fn check_sequence(mut encoder: Encoder, mut decoder: Decoder, steps: &[Step]) {
+        for (i, (wire, headers, entries, size)) in steps.iter().enumerate() {
+            let expected: Vec<(usize, String, String)> = entries.iter()
+                .map(|(index, name, value)| (*index, name.to_string(), value.to_string()))
+                .collect();
+
+            assert_eq!(encoder.encode(headers), hex::decode(wire).unwrap(), "step {}", i + 1);
+            assert_eq!(table(encoder.header_table()), expected, "step {}", i + 1);
+            assert_eq!(encoder.header_table().size(), *size, "step {}", i + 1);
+
+            assert_eq!(&decoder.decode(&hex::decode(wire).unwrap()).unwrap(), headers, "step {}", i + 1);
+            assert_eq!(table(decoder.header_table()), expected, "step {}", i + 1);
+            assert_eq!(decoder.header_table().size(), *size, "step {}", i + 1);
+        }
+    }
+
+    fn requests() -> [Vec<Header>; 3] {
+        [
+            vec![
+                Header::new(":method", "GET"),
+                Header::new(":scheme", "http"),
+                Header::new(":path", "/"),
+                Header::new(":authority", "www.example.com"),
+            ],
+            vec![
+                Header::new(":method", "GET"),
+                Header::new(":scheme", "http"),
+                Header::new(":path", "/"),
+                Header::new(":authority", "www.example.com"),
+                Header::new("cache-control", "no-cache"),
+            ],
+            vec![
+                Header::new(":method", "GET"),
+                Header::new(":scheme", "https"),
+                Header::new(":path", "/index.html"),
+                Header::new(":authority", "www.example.com"),
+                Header::new("custom-key", "custom-value"),
+            ],
+        ]
+    }
+
+    fn responses() -> [Vec<Header>; 3] {
+        [
+            vec![
+                Header::new(":status", "302"),
+                Header::new("cache-control", "private"),
+                Header::new("date", "Mon, 21 Oct 2013 20:13:21 GMT"),
+                Header::new("location", "https://www.example.com"),
+            ],
+            vec![
+                Header::new(":status", "307"),
+                Header::new("cache-control", "private"),
+                Header::new("date", "Mon, 21 Oct 2013 20:13:21 GMT"),
+                Header::new("location", "https://www.example.com"),
+            ],
+            vec![
+                Header::new(":status", "200"),
+                Header::new("cache-control", "private"),
+                Header::new("date", "Mon, 21 Oct 2013 20:13:22 GMT"),
+                Header::new("location", "https://www.example.com"),
+                Header::new("content-encoding", "gzip"),
+                Header::new("set-cookie", "foo=ASDJKHQKBZXOQWEOPIUAXQWEOIU; max-age=3600; version=1"),
+            ],
+        ]
+    }
+
+    fn request_tables() -> [Table<'static>; 3] {
+        [
+            (vec![(62, ":authority", "www.example.com")], 57),
+            (vec![(62, "cache-control", "no-cache"), (63, ":authority", "www.example.com")], 110),
+            (vec![
+                (62, "custom-key", "custom-value"),
+                (63, "cache-control", "no-cache"),
+                (64, ":authority", "www.example.com"),
+            ], 164),
+        ]
+    }
+
+    fn response_tables() -> [Table<'static>; 3] {
+        [
+            (vec![
+                (62, "location", "https://www.example.com"),
+                (63, "date", "Mon, 21 Oct 2013 20:13:21 GMT"),
+                (64, "cache-control", "private"),
+                (65, ":status", "302"),
+            ], 222),
+            (vec![
+                (62, ":status", "307"),
+                (63, "location", "https://www.example.com"),
+                (64, "date", "Mon, 21 Oct 2013 20:13:21 GMT"),
+                (65, "cache-control", "private"),
+            ], 222),
+            (vec![
+                (62, "set-cookie", "foo=ASDJKHQKBZXOQWEOPIUAXQWEOIU; max-age=3600; version=1"),
+                (63, "content-encoding", "gzip"),
+                (64, "date", "Mon, 21 Oct 2013 20:13:22 GMT"),
+            ], 215),
+        ]
+    }
+
+    fn steps<'a>(
+        wires: [&'a str; 3],
+        headers: [Vec<Header>; 3],
+        tables: [Table<'a>; 3],
+    ) -> Vec<Step<'a>> {
+        wires.into_iter().zip(headers).zip(tables)
+            .map(|((wire, headers), (entries, size))| (wire, headers, entries, size))
+            .collect()
+    }
+
+    #[test]
+    fn test_c1_integers() {
+        let encoder = Encoder::new();
+        let decoder = Decoder::new();
+        let cases: [(u64, u32, &[u8]); 3] = [
+            (10, 5, &[0b01010]),
+            (1337, 5, &[0b11111, 0b10011010, 0b00001010]),
+            (42, 8, &[0b00101010]),
+        ];
+
+        for (value, prefix, encoded) in cases {
+            assert_eq!(encoder.encode_int(value, prefix), encoded.to_vec());
+            assert_eq!(decoder.decode_int(encoded, prefix), Ok((value, encoded.len())));
+        }
+    }
+
+    #[test]
+    fn test_c2_1_literal_with_indexing() {
+        let wire = "400a 6375 7374 6f6d 2d6b 6579 0d63 7573 746f 6d2d 6865 6164 6572";
+        check_sequence(Encoder::new(), Decoder::new(), &[(
+            wire,
+            vec![Header::new("custom-key", "custom-header")],
+            vec![(62, "custom-key", "custom-header")],
+            55,
+        )]);
+    }
+
+    #[test]
+    fn test_c2_2_literal_without_indexing() {
+        let encoder = Encoder::builder().indexing(IndexingStrategy::WithoutIndexing).build();
+        let wire = "040c 2f73 616d 706c 652f 7061 7468";
+        check_sequence(encoder, Decoder::new(), &[(wire, vec![Header::new(":path", "/sample/path")], vec![], 0)]);
+    }
+
+    #[test]
+    fn test_c2_3_literal_never_indexed() {
+        let wire = hex::decode("1008 7061 7373 776f 7264 0673 6563 7265 74").unwrap();
+        let headers = [EncodableHeader::new("password", "secret", true)];
+
+        let mut encoder = Encoder::new();
+        assert_eq!(encoder.encode_headers(&headers), wire);
+        assert!(encoder.header_table().is_empty());
+
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.decode_headers(&wire).unwrap(), headers);
+        assert!(decoder.header_table().is_empty());
+    }
+
+    #[test]
+    fn test_c2_4_indexed() {
+        check_sequence(Encoder::new(), Decoder::new(), &[("82", vec![Header::new(":method", "GET")], vec![], 0)]);
+    }
+
+    #[test]
+    fn test_c3_requests_without_huffman() {
+        let wires = [
+            "8286 8441 0f77 7777 2e65 7861 6d70 6c65 2e63 6f6d",
+            "8286 84be 5808 6e6f 2d63 6163 6865",
+            "8287 85bf 400a 6375 7374 6f6d 2d6b 6579 0c63 7573 746f 6d2d 7661 6c75 65",
+        ];
+        check_sequence(Encoder::new(), Decoder::new(), &steps(wires, requests(), request_tables()));
+    }
+
+    #[test]
+    fn test_c4_requests_with_huffman() {
+        let wires = [
+            "8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff",
+            "8286 84be 5886 a8eb 1064 9cbf",
+            "8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf",
+        ];
+        let encoder = Encoder::builder().huffman(HuffmanPolicy::Always).build();
+        check_sequence(encoder, Decoder::new(), &steps(wires, requests(), request_tables()));
+    }
+
+    #[test]
+    fn test_c5_responses_without_huffman() {
+        let wires = [
+            "4803 3330 3258 0770 7269 7661 7465 611d 4d6f 6e2c 2032 3120 4f63 7420 3230 3133 \
+             2032 303a 3133 3a32 3120 474d 546e 1768 7474 7073 3a2f 2f77 7777 2e65 7861 6d70 \
+             6c65 2e63 6f6d",
+            "4803 3330 37c1 c0bf",
+            "88c1 611d 4d6f 6e2c 2032 3120 4f63 7420 3230 3133 2032 303a 3133 3a32 3220 474d \
+             54c0 5a04 677a 6970 7738 666f 6f3d 4153 444a 4b48 514b 425a 584f 5157 454f 5049 \
+             5541 5851 5745 4f49 553b 206d 6178 2d61 6765 3d33 3630 303b 2076 6572 7369 6f6e \
+             3d31",
+        ];
+        let encoder = Encoder::builder().max_header_table_size(256).header_table_size(256).build();
+        let decoder = Decoder::builder().max_header_table_size(256).build();
+        check_sequence(encoder, decoder, &steps(wires, responses(), response_tables()));
+    }
+
+    #[test]
+    fn test_c6_responses_with_huffman() {
+        let wires = [
+            "4882 6402 5885 aec3 771a 4b61 96d0 7abe 9410 54d4 44a8 2005 9504 0b81 66e0 82a6 \
+             2d1b ff6e 919d 29ad 1718 63c7 8f0b 97c8 e9ae 82ae 43d3",
+            "4883 640e ffc1 c0bf",
+            "88c1 6196 d07a be94 1054 d444 a820 0595 040b 8166 e084 a62d 1bff c05a 839b d9ab \
+             77ad 94e7 821d d7f2 e6c7 b335 dfdf cd5b 3960 d5af 2708 7f36 72c1 ab27 0fb5 291f \
+             9587 3160 65c0 03ed 4ee5 b106 3d50 07",
+        ];
+        let encoder = Encoder::builder()
+            .max_header_table_size(256)
+            .header_table_size(256)
+            .huffman(HuffmanPolicy::Always)
+            .build();
+        let decoder = Decoder::builder().max_header_table_size(256).build();
+        check_sequence(encoder, decoder, &steps(wires, responses(), response_tables()));
+    }
+}
+
+#[cfg(test)]
+mod proptest_tests {
+    use proptest::prelude::*;
+    use crate::hpack::decoder::{Decoder, RepresentationKind};
+    use crate::hpack::encoder::{Encoder, HuffmanPolicy, IndexingStrategy};
+    use crate::hpack::header::EncodableHeader;
+    use crate::hpack::huffman;
+
+    fn huffman_policy() -> impl Strategy<Value = HuffmanPolicy> {
+        prop_oneof![Just(HuffmanPolicy::Never), Just(HuffmanPolicy::Always), Just(HuffmanPolicy::Shortest)]
+    }
+
+    // a small name pool so later headers hit the dynamic table
+    fn header() -> impl Strategy<Value = EncodableHeader> {
+        let name = prop_oneof![
+            Just(":path".to_string()),
+            Just("cookie".to_string()),
+            Just("x-custom".to_string()),
+            "[a-z-]{1,12}",
+        ];
+        let value = prop_oneof![Just("".to_string()), "[a-z0-9]{1,4}", ".{0,64}"];
+        (name, value, any::<bool>()).prop_map(|(name, value, is_sensitive)| EncodableHeader::new(name, value, is_sensitive))
+    }
+
+    proptest! {
+        #[test]
+        fn prop_int_round_trip(value in prop_oneof![0..1024u64, any::<u64>(), Just(u64::MAX)], prefix in 1..=8u32, flags in any::<u8>()) {
+            let encoder = Encoder::new();
+            let decoder = Decoder::new();
+
+            let mut encoded = encoder.encode_int(value, prefix);
+            // bits above the prefix belong to the representation and must be ignored
+            encoded[0] |= flags & !(0xff >> (8 - prefix));
+            prop_assert_eq!(decoder.decode_int(&encoded, prefix), Ok((value, encoded.len())));
+            prop_assert!(decoder.decode_int(&encoded[..encoded.len() - 1], prefix).is_err());
+        }
+
+        #[test]
+        fn prop_string_round_trip(name in ".{0,32}", value in ".{0,256}", huffman in huffman_policy()) {
+            let mut encoder = Encoder::builder().huffman(huffman).build();
+            let mut decoder = Decoder::new();
+
+            let mut encoded = vec![0];
+            encoded.append(&mut encoder.encode_string(&name));
+            encoded.append(&mut encoder.encode_string(&value));
+
+            let representation = decoder.decode_representation(&encoded).unwrap();
+            let header = representation.header.unwrap();
+            prop_assert_eq!(representation.kind, RepresentationKind::WithoutIndexing);
+            prop_assert_eq!(representation.length, encoded.len());
+            let value_huffman = match huffman {
+                HuffmanPolicy::Never => false,
+                HuffmanPolicy::Always => true,
+                HuffmanPolicy::Shortest => huffman::encoded_len(value.as_bytes()) < value.len(),
+            };
+            prop_assert_eq!(representation.value_huffman, value_huffman);
+            prop_assert_eq!(header.name.as_str(), name.as_str());
+            prop_assert_eq!(header.value, value);
+        }
+
+        #[test]
+        fn prop_table_size_updates(
+            max_size in 0..512usize,
+            huffman in huffman_policy(),
+            without_indexing in any::<bool>(),
+            blocks in prop::collection::vec(
+                (prop::collection::vec(0..600usize, 0..3), prop::collection::vec(header(), 0..8)),
+                1..12,
+            ),
+        ) {
+            let indexing = if without_indexing { IndexingStrategy::WithoutIndexing } else { IndexingStrategy::Incremental };
+            let mut encoder = Encoder::builder()
+                .max_header_table_size(max_size)
+                .header_table_size(max_size)
+                .huffman(huffman)
+                .indexing(indexing)
+                .build();
+            let mut decoder = Decoder::builder().max_header_table_size(max_size).build();
+
+            for (sizes, headers) in blocks {
+                for size in sizes {
+                    encoder.set_header_table_size(size);
+                }
+
+                let encoded = encoder.encode_headers(&headers);
+                prop_assert_eq!(decoder.decode_headers(&encoded).unwrap(), headers);
+                prop_assert_eq!(decoder.header_table().snapshot(), encoder.header_table().snapshot());
+                prop_assert!(encoder.header_table().size() <= encoder.header_table_size());
+            }
+        }
     }
 }
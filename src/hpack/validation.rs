@@ -0,0 +1,133 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::hpack::header::{EncodableHeader, Header};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validation {
+    Disabled,
+    // name and value characters only
+    Fields,
+    // field characters plus the header list rules of RFC 7540 section 8.1.2
+    Strict,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    EmptyName,
+    UppercaseName(String),
+    InvalidName(String),
+    InvalidValue(String),
+    UnknownPseudoHeader(String),
+    DuplicatePseudoHeader(String),
+    PseudoHeaderAfterRegular(String),
+    MixedPseudoHeaders(String),
+    ConnectionSpecific(String),
+    InvalidTe(String),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::EmptyName => write!(f, "Empty header name!"),
+            ValidationError::UppercaseName(x) => write!(f, "Uppercase header name: {}", x),
+            ValidationError::InvalidName(x) => write!(f, "Invalid header name: {}", x),
+            ValidationError::InvalidValue(x) => write!(f, "Invalid value for header: {}", x),
+            ValidationError::UnknownPseudoHeader(x) => write!(f, "Unknown pseudo-header: {}", x),
+            ValidationError::DuplicatePseudoHeader(x) => write!(f, "Duplicate pseudo-header: {}", x),
+            ValidationError::PseudoHeaderAfterRegular(x) => write!(f, "Pseudo-header after regular header: {}", x),
+            ValidationError::MixedPseudoHeaders(x) => write!(f, "Request and response pseudo-headers mixed: {}", x),
+            ValidationError::ConnectionSpecific(x) => write!(f, "Connection-specific header: {}", x),
+            ValidationError::InvalidTe(x) => write!(f, "Invalid te value: {}", x),
+        }
+    }
+}
+
+impl core::error::Error for ValidationError {}
+
+const REQUEST_PSEUDO_HEADERS: [&str; 5] = [":method", ":scheme", ":authority", ":path", ":protocol"];
+const RESPONSE_PSEUDO_HEADERS: [&str; 1] = [":status"];
+const CONNECTION_SPECIFIC_HEADERS: [&str; 5] = [
+    "connection", "keep-alive", "proxy-connection", "transfer-encoding", "upgrade",
+];
+
+pub fn validate(headers: &[Header], validation: Validation) -> Result<(), ValidationError> {
+    validate_fields(headers.iter().map(|x| (x.name.as_str(), x.value.as_str())), validation)
+}
+
+pub fn validate_encodable(headers: &[EncodableHeader], validation: Validation) -> Result<(), ValidationError> {
+    validate_fields(headers.iter().map(|x| (x.name.as_str(), x.value.as_str())), validation)
+}
+
+fn validate_fields<'a, I>(fields: I, validation: Validation) -> Result<(), ValidationError>
+where I: Iterator<Item = (&'a str, &'a str)> {
+    if validation == Validation::Disabled {
+        return Ok(());
+    }
+
+    let mut pseudo_headers: Vec<&str> = vec![];
+    let mut regular_seen = false;
+
+    for (name, value) in fields {
+        validate_name(name)?;
+        if value.bytes().any(|x| x == b'\0' || x == b'\r' || x == b'\n') {
+            return Err(ValidationError::InvalidValue(name.to_string()));
+        }
+
+        if validation != Validation::Strict {
+            continue;
+        }
+
+        if name.starts_with(':') {
+            if regular_seen {
+                return Err(ValidationError::PseudoHeaderAfterRegular(name.to_string()));
+            }
+
+            let is_request = REQUEST_PSEUDO_HEADERS.contains(&name);
+            if !is_request && !RESPONSE_PSEUDO_HEADERS.contains(&name) {
+                return Err(ValidationError::UnknownPseudoHeader(name.to_string()));
+            }
+            if pseudo_headers.contains(&name) {
+                return Err(ValidationError::DuplicatePseudoHeader(name.to_string()));
+            }
+            if pseudo_headers.iter().any(|x| REQUEST_PSEUDO_HEADERS.contains(x) != is_request) {
+                return Err(ValidationError::MixedPseudoHeaders(name.to_string()));
+            }
+
+            pseudo_headers.push(name);
+            continue;
+        }
+
+        regular_seen = true;
+        if CONNECTION_SPECIFIC_HEADERS.contains(&name) {
+            return Err(ValidationError::ConnectionSpecific(name.to_string()));
+        }
+        if name == "te" && value != "trailers" {
+            return Err(ValidationError::InvalidTe(value.to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<(), ValidationError> {
+    let token = name.strip_prefix(':').unwrap_or(name);
+    if token.is_empty() {
+        return Err(ValidationError::EmptyName);
+    }
+
+    for byte in token.bytes() {
+        if byte.is_ascii_uppercase() {
+            return Err(ValidationError::UppercaseName(name.to_string()));
+        }
+
+        // RFC 7230 section 3.2.6 token characters
+        let is_token = byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte);
+        if !is_token {
+            return Err(ValidationError::InvalidName(name.to_string()));
+        }
+    }
+
+    Ok(())
+}
@@ -1,2 +1,6 @@
-extern crate lazy_static;
-mod hpack;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+pub mod connection;
+pub mod frame;
+pub mod hpack;